@@ -0,0 +1,292 @@
+/// The key of an object entry in a `:class` or `:style` binding
+#[derive(Debug, PartialEq)]
+pub(crate) enum Key {
+    /// A name or quoted string, e.g. `active` or `'text-danger'`
+    Literal(String),
+    /// A Rust expression in brackets, e.g. `[style.active]`
+    Computed(String),
+}
+
+/// A part of a `:class` or `:style` binding
+#[derive(Debug, PartialEq)]
+pub(crate) enum BindingPart {
+    /// An entry of an object such as `{ active: is_active }`
+    Entry { key: Key, value: String },
+    /// Any other Rust expression, a class list or style declarations
+    Expression(String),
+}
+
+/// Splits the value of a `:class` or `:style` binding into its parts. Like in
+/// Vue it may be an object, an array of objects and expressions, or a single
+/// expression.
+pub(crate) fn parse_binding(value: &str) -> Result<Vec<BindingPart>, String> {
+    let value = value.trim();
+
+    if let Some(items) = enclosed(value, '[', ']') {
+        let mut parts = Vec::new();
+        for item in split_top_level(items, ',') {
+            match item.trim() {
+                "" => {}
+                item if enclosed(item, '{', '}').is_some() => parts.extend(parse_object(item)?),
+                item => parts.push(BindingPart::Expression(item.to_string())),
+            }
+        }
+
+        return Ok(parts);
+    }
+
+    if enclosed(value, '{', '}').is_some() {
+        return parse_object(value);
+    }
+
+    Ok(vec![BindingPart::Expression(value.to_string())])
+}
+
+/// Parses an object such as `{ active: is_active, 'text-danger': has_error }`
+fn parse_object(object: &str) -> Result<Vec<BindingPart>, String> {
+    let entries = object
+        .strip_prefix('{')
+        .and_then(|o| o.strip_suffix('}'))
+        .ok_or_else(|| format!("Unterminated object in binding: {object}"))?;
+
+    let mut parts = Vec::new();
+    for entry in split_top_level(entries, ',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let (key, value) = match find_colon(entry) {
+            Some(colon) => (entry[..colon].trim(), entry[colon + 1..].trim()),
+            // Shorthand such as `{ active }`
+            None => (entry, entry),
+        };
+
+        let key = if let Some(computed) = key.strip_prefix('[').and_then(|k| k.strip_suffix(']')) {
+            Key::Computed(computed.trim().to_string())
+        } else if let Some(quoted) = unquote(key) {
+            Key::Literal(quoted.to_string())
+        } else if key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        {
+            Key::Literal(key.to_string())
+        } else {
+            return Err(format!("Invalid key in binding: {key}"));
+        };
+
+        if value.is_empty() {
+            return Err(format!("Missing value in binding entry: {entry}"));
+        }
+        parts.push(BindingPart::Entry {
+            key,
+            value: value.to_string(),
+        });
+    }
+
+    Ok(parts)
+}
+
+/// Returns what is between `open` and `close` if the bracket opening `value`
+/// closes at its end. `[a, b][0]` for example is not an array but an index.
+pub(crate) fn enclosed(value: &str, open: char, close: char) -> Option<&str> {
+    let inner = value.strip_prefix(open)?;
+    let mut depth = 1usize;
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (index, c) in inner.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    let end = index + c.len_utf8();
+                    return (c == close && end == inner.len()).then(|| &inner[..index]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn unquote(key: &str) -> Option<&str> {
+    ['\'', '"']
+        .into_iter()
+        .find_map(|quote| key.strip_prefix(quote).and_then(|k| k.strip_suffix(quote)))
+}
+
+/// Finds the `:` separating key and value, skipping `::` of Rust paths
+fn find_colon(entry: &str) -> Option<usize> {
+    let bytes = entry.as_bytes();
+    let mut depth = 0usize;
+    let mut quote = None;
+
+    for (index, &byte) in bytes.iter().enumerate() {
+        match (quote, byte) {
+            (Some(q), _) if byte == q => quote = None,
+            (Some(_), _) => {}
+            (None, b'"' | b'\'') => quote = Some(byte),
+            (None, b'(' | b'[' | b'{') => depth += 1,
+            (None, b')' | b']' | b'}') => depth = depth.saturating_sub(1),
+            (None, b':') if depth == 0 => {
+                let is_path = bytes.get(index + 1) == Some(&b':')
+                    || index.checked_sub(1).map(|i| bytes[i]) == Some(b':');
+                if !is_path {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Splits at every `separator` outside of brackets and strings
+fn split_top_level(input: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (index, c) in input.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => depth = depth.saturating_sub(1),
+            (None, _) if c == separator && depth == 0 => {
+                parts.push(&input[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&input[start..]);
+
+    parts
+}
+
+/// Turns the camel case name of a style property into its CSS name, e.g.
+/// `fontSize` into `font-size`. Custom properties are kept as they are.
+pub(crate) fn css_property(name: &str) -> String {
+    if name.starts_with("--") {
+        return name.to_string();
+    }
+
+    let mut property = String::with_capacity(name.len() + 2);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            property.push('-');
+            property.push(c.to_ascii_lowercase());
+        } else {
+            property.push(c);
+        }
+    }
+
+    property
+}
+
+/// Turns a kebab case name into camel case for `.camel`, e.g. `view-box` into
+/// `viewBox`
+pub(crate) fn camel_case(name: &str) -> String {
+    let mut camel = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        match c {
+            '-' => upper = true,
+            c if upper => {
+                camel.push(c.to_ascii_uppercase());
+                upper = false;
+            }
+            c => camel.push(c),
+        }
+    }
+
+    camel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: &str, value: &str) -> BindingPart {
+        BindingPart::Entry {
+            key: Key::Literal(key.to_string()),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parses_object() {
+        assert_eq!(
+            parse_binding("{ active: is_active, 'text-danger': state::has_error(), hidden }"),
+            Ok(vec![
+                entry("active", "is_active"),
+                entry("text-danger", "state::has_error()"),
+                entry("hidden", "hidden"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parses_array() {
+        assert_eq!(
+            parse_binding("[style.card, { [style.active]: active }, \"a, b\"]"),
+            Ok(vec![
+                BindingPart::Expression("style.card".to_string()),
+                BindingPart::Entry {
+                    key: Key::Computed("style.active".to_string()),
+                    value: "active".to_string(),
+                },
+                BindingPart::Expression("\"a, b\"".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parses_expression() {
+        assert_eq!(
+            parse_binding(" classes "),
+            Ok(vec![BindingPart::Expression("classes".to_string())])
+        );
+        assert_eq!(
+            parse_binding("[a, b][0]"),
+            Ok(vec![BindingPart::Expression("[a, b][0]".to_string())])
+        );
+        assert_eq!(
+            parse_binding("[\"]\", b]"),
+            Ok(vec![
+                BindingPart::Expression("\"]\"".to_string()),
+                BindingPart::Expression("b".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_entries() {
+        assert!(parse_binding("{ a b: c }").is_err());
+        assert!(parse_binding("{ a: }").is_err());
+    }
+
+    #[test]
+    fn test_converts_style_properties() {
+        assert_eq!(css_property("fontSize"), "font-size");
+        assert_eq!(css_property("font-size"), "font-size");
+        assert_eq!(css_property("--mainColor"), "--mainColor");
+        assert_eq!(camel_case("view-box"), "viewBox");
+        assert_eq!(camel_case("viewBox"), "viewBox");
+    }
+}
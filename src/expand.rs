@@ -1,36 +1,1378 @@
+use std::path::Path;
+
 use proc_macro::TokenStream;
+use syn::punctuated::Punctuated;
+use syn::visit::{self, Visit};
+use syn::Token;
 
+use crate::binding::{camel_case, css_property, enclosed, parse_binding, BindingPart, Key};
+use crate::options::CompileOptions;
 use crate::parser::{
-    parser::{Node, NodeType, Parser},
-    scanner::Scanner,
-    token::Token,
+    parser::{Node, NodeType, Parser, ParserError},
+    scanner::{Scanner, ScannerError},
+    sfc::{SfcBlock, SfcDescriptor},
+    token::Directive,
+    whitespace::condense_whitespace,
 };
+use crate::runtime;
+use crate::style;
+
+/// Turns each diagnostic into its own `compile_error!` so they are all reported
+fn compile_errors(diagnostics: &[String]) -> TokenStream {
+    diagnostics
+        .iter()
+        .map(|diagnostic| format!("compile_error!({diagnostic:?});"))
+        .collect::<String>()
+        .parse()
+        .unwrap()
+}
+
+/// Only scripts written in Rust become part of the generated code
+fn is_rust(block: &SfcBlock) -> bool {
+    matches!(block.lang(), Some("rust") | Some("rs"))
+}
+
+/// Like top-level `await` in Vue's `<script setup>`, awaiting in the statements
+/// of the script makes the component async. Awaits in closures, async blocks and
+/// nested items belong to other futures.
+fn is_async(script: &str) -> bool {
+    let Ok(block) = syn::parse_str::<syn::Block>(&format!("{{{script}}}")) else {
+        return false;
+    };
+
+    let mut awaits = TopLevelAwait(false);
+    awaits.visit_block(&block);
+    awaits.0
+}
+
+struct TopLevelAwait(bool);
+
+impl<'ast> Visit<'ast> for TopLevelAwait {
+    fn visit_expr_await(&mut self, _: &'ast syn::ExprAwait) {
+        self.0 = true;
+    }
+
+    fn visit_expr_async(&mut self, _: &'ast syn::ExprAsync) {}
+
+    fn visit_expr_closure(&mut self, _: &'ast syn::ExprClosure) {}
+
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+}
+
+/// The `web_sys` types of the elements a `ref` can point to, others are `HtmlElement`
+const ELEMENT_TYPES: [(&str, &str); 21] = [
+    ("a", "HtmlAnchorElement"),
+    ("audio", "HtmlAudioElement"),
+    ("button", "HtmlButtonElement"),
+    ("canvas", "HtmlCanvasElement"),
+    ("dialog", "HtmlDialogElement"),
+    ("div", "HtmlDivElement"),
+    ("form", "HtmlFormElement"),
+    ("iframe", "HtmlIFrameElement"),
+    ("img", "HtmlImageElement"),
+    ("input", "HtmlInputElement"),
+    ("label", "HtmlLabelElement"),
+    ("li", "HtmlLiElement"),
+    ("ol", "HtmlOListElement"),
+    ("option", "HtmlOptionElement"),
+    ("p", "HtmlParagraphElement"),
+    ("select", "HtmlSelectElement"),
+    ("span", "HtmlSpanElement"),
+    ("table", "HtmlTableElement"),
+    ("textarea", "HtmlTextAreaElement"),
+    ("ul", "HtmlUListElement"),
+    ("video", "HtmlVideoElement"),
+];
+
+/// The type of the handle of a `ref` on `tag`
+fn ref_type(tag: &str) -> String {
+    if is_component(tag) {
+        return "villain_runtime::ComponentRef".to_string();
+    }
+
+    let element = ELEMENT_TYPES
+        .iter()
+        .find(|(name, _)| *name == tag)
+        .map_or("HtmlElement", |(_, ty)| ty);
+    format!("villain_runtime::TemplateRef<web_sys::{element}>")
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Directives the compiler handles itself, all others have to be registered
+const BUILTIN_DIRECTIVES: [&str; 9] = [
+    "bind", "cloak", "html", "memo", "model", "once", "pre", "show", "text",
+];
+
+/// Vue's directives the compiler does not handle yet. They are reported instead
+/// of being mistaken for custom directives.
+const UNSUPPORTED_DIRECTIVES: [&str; 7] = ["else", "else-if", "for", "if", "is", "on", "slot"];
+
+/// Tags starting with an uppercase letter such as `<TodoItem>` are components
+fn is_component(tag: &str) -> bool {
+    tag.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+/// Components are looked up next to the file using them, `<TodoItem>` in
+/// `src/App.vue` resolves to `src/TodoItem.vue`
+fn resolve_component(parent: &str, tag: &str) -> String {
+    Path::new(parent)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(format!("{tag}.vue"))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Identifiers such as `TabA` that Rust code uses as values, which may name
+/// components. Names the code declares itself are skipped. Code that does not
+/// parse names none, rustc reports its errors.
+fn pascal_case_identifiers(code: &str) -> Vec<String> {
+    let Ok(block) = syn::parse_str::<syn::Block>(&format!("{{{code}}}")) else {
+        return Vec::new();
+    };
+
+    let mut identifiers = PascalCaseIdentifiers::default();
+    identifiers.visit_block(&block);
+    let PascalCaseIdentifiers { used, declared } = identifiers;
+
+    used.into_iter()
+        .filter(|name| !declared.contains(name))
+        .collect()
+}
+
+#[derive(Default)]
+struct PascalCaseIdentifiers {
+    used: Vec<String>,
+    declared: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for PascalCaseIdentifiers {
+    fn visit_expr_path(&mut self, expr: &'ast syn::ExprPath) {
+        if let Some(ident) = expr.path.get_ident().filter(|_| expr.qself.is_none()) {
+            let name = ident.to_string();
+            if is_component(&name) {
+                self.used.push(name);
+            }
+        }
+        visit::visit_expr_path(self, expr);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        // Arguments of macros such as `vec![TabA, TabB]` are only tokens to syn
+        if let Ok(arguments) =
+            mac.parse_body_with(Punctuated::<syn::Expr, Token![,]>::parse_terminated)
+        {
+            for argument in &arguments {
+                self.visit_expr(argument);
+            }
+        }
+        visit::visit_macro(self, mac);
+    }
+
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        let ident = match item {
+            syn::Item::Const(item) => Some(&item.ident),
+            syn::Item::Enum(item) => Some(&item.ident),
+            syn::Item::Fn(item) => Some(&item.sig.ident),
+            syn::Item::Static(item) => Some(&item.ident),
+            syn::Item::Struct(item) => Some(&item.ident),
+            syn::Item::Type(item) => Some(&item.ident),
+            syn::Item::Union(item) => Some(&item.ident),
+            _ => None,
+        };
+        self.declared.extend(ident.map(ToString::to_string));
+        visit::visit_item(self, item);
+    }
+}
+
+/// The children of a built-in such as `<KeepAlive>` besides attributes and whitespace
+fn content(node: &Node) -> Vec<&Node> {
+    node.children
+        .iter()
+        .filter(|child| {
+            !child.is_attribute()
+                && !matches!(&child.node_type, NodeType::Text(text) if text.trim_ascii().is_empty())
+        })
+        .collect()
+}
+
+/// Name of the render function of the component at `path`
+fn render_function(path: &str) -> String {
+    let name: String = Path::new(path)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    format!("render_{name}_{}", style::scope_id(path))
+}
 
 /// Expands the template file into a function that can be used to render the template
 /// this function is used by the `create_component` and `create_entypoint` proc_macros
-pub fn expand_template(path: String) -> TokenStream {
-    let template_path_opt = if path.is_empty() {
-        None
-    } else {
-        Some(path.replace('"', ""))
-    };
-    let template_path = template_path_opt.unwrap_or_else(|| "src/App.vue".to_string());
+pub fn expand_template(options: CompileOptions) -> TokenStream {
+    let template_path = options.path.clone();
     let template = std::fs::read_to_string(template_path.clone())
         .unwrap_or_else(|_| panic!("Could not read template file: {template_path}"));
 
-    let scanner = Scanner::new(template);
-    let tokens: Vec<Token> = scanner.try_into().unwrap();
-    let parser = Parser::new(tokens);
-    let mut code: String = "".into();
+    match compile(template, &options) {
+        Ok(code) => code.parse().unwrap(),
+        Err(diagnostics) => compile_errors(&diagnostics),
+    }
+}
+
+/// Compiles the source of a single file component and all components used by
+/// it into the `template` function rendering them
+fn compile(template: String, options: &CompileOptions) -> Result<String, Vec<String>> {
+    let mut compiler = Compiler {
+        options,
+        components: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    compiler.component(&options.path, template);
+    if !compiler.diagnostics.is_empty() {
+        return Err(compiler.diagnostics);
+    }
+
+    let stylesheet = compiler.bundle();
+    let functions: String = compiler
+        .components
+        .iter()
+        .map(|component| component.code.as_str())
+        .collect();
+    let entrypoint = &compiler.components[0].function;
+
+    // With a bundle the page links the stylesheet itself, otherwise the styles
+    // are added to the document once, on the first mount
+    let inject_style = match &options.css_bundle {
+        Some(file) => {
+            let out_dir = std::env::var("OUT_DIR").map_err(|_| {
+                vec![format!(
+                    "Writing the CSS bundle {file} requires OUT_DIR, add a build script to the crate"
+                )]
+            })?;
+            write_bundle(Path::new(&out_dir), file, &stylesheet).map_err(|e| vec![e])?;
+
+            String::new()
+        }
+        None if stylesheet.is_empty() => String::new(),
+        None => {
+            let scope_id = style::scope_id(&options.path);
+            format!(
+                "
+            if document.get_element_by_id(\"villain-style-{scope_id}\").is_none() {{
+                let style = document.create_element(\"style\").unwrap();
+                style.set_id(\"villain-style-{scope_id}\");
+                style.set_text_content(Some(STYLE));
+                document.head().unwrap().append_child(&style).unwrap();
+            }}"
+            )
+        }
+    };
+
+    Ok(format!(
+        "/// The styles of all components used by the template, e.g. to add them to
+        /// server side rendered pages
+        const STYLE: &str = {stylesheet:?};
+
+        {runtime}
+
+        fn template(document: web_sys::Document, root: web_sys::Element) {{
+            {functions}
+
+            let document = &document;
+            {inject_style}
+            {entrypoint}(document, root);
+        }}",
+        runtime = runtime::module(),
+    ))
+}
+
+/// Writes the bundled styles to `file` below `out_dir`. An unchanged bundle is
+/// not rewritten, so tools watching it only see actual changes.
+fn write_bundle(out_dir: &Path, file: &str, css: &str) -> Result<(), String> {
+    let path = out_dir.join(file);
+    if std::fs::read_to_string(&path).is_ok_and(|current| current == css) {
+        return Ok(());
+    }
+
+    path.parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, css))
+        .map_err(|e| format!("Could not write CSS bundle {}: {e}", path.display()))
+}
+
+/// A component compiled into a render function of its own
+struct Component {
+    path: String,
+    /// Name of the render function
+    function: String,
+    /// The render function, taking the document and the element to render into
+    code: String,
+    /// The CSS of each `<style>` block, scoped if needed
+    styles: Vec<String>,
+}
+
+/// Compiles a component and every component reachable from its template
+struct Compiler<'a> {
+    options: &'a CompileOptions,
+    /// Compiled components in the order they are first used, the entrypoint first
+    components: Vec<Component>,
+    diagnostics: Vec<String>,
+}
+
+impl Compiler<'_> {
+    /// Compiles the component at `path` unless it already is, and then the
+    /// components used by it
+    fn component(&mut self, path: &str, source: String) {
+        if self
+            .components
+            .iter()
+            .any(|component| component.path == path)
+        {
+            return;
+        }
+
+        // Registered before its children are compiled, so recursive components terminate
+        let index = self.components.len();
+        self.components.push(Component {
+            path: path.to_string(),
+            function: render_function(path),
+            code: String::new(),
+            styles: Vec::new(),
+        });
+
+        let used = match self.render(index, source) {
+            Ok(used) => used,
+            Err(diagnostics) => {
+                self.diagnostics
+                    .extend(diagnostics.into_iter().map(|d| format!("{path}: {d}")));
+                return;
+            }
+        };
+
+        for (tag, child) in used {
+            match std::fs::read_to_string(&child) {
+                Ok(source) => self.component(&child, source),
+                Err(e) => self.diagnostics.push(format!(
+                    "{path}: Could not resolve component <{tag}> at {child}: {e}"
+                )),
+            }
+        }
+    }
+
+    /// Generates the render function of a component and returns the tags and
+    /// paths of the components it uses
+    fn render(
+        &mut self,
+        index: usize,
+        source: String,
+    ) -> Result<Vec<(String, String)>, Vec<String>> {
+        let path = self.components[index].path.clone();
+        let scanner = match &self.options.delimiters {
+            Some(delimiters) => Scanner::with_delimiters(source, delimiters.clone()),
+            None => Scanner::new(source),
+        }
+        .sfc();
+        let (tokens, scanner_errors) = scanner.scan_recovering();
+
+        // An unterminated interpolation with no tag after it runs into the end of
+        // the file, so the tags left open there are not reported on top of it
+        let ends_in_interpolation = scanner_errors.iter().any(|e| {
+            matches!(e, ScannerError::UnterminatedInterpolation(position)
+                if tokens.last().is_none_or(|token| token.position < *position))
+        });
+        let parser = Parser::new(tokens);
+
+        let (root, parser_errors) = parser.parse_recovering();
+
+        // Report every problem found in the template at once instead of only the first one
+        let diagnostics: Vec<String> = scanner_errors
+            .iter()
+            .map(ToString::to_string)
+            .chain(
+                parser_errors
+                    .iter()
+                    .filter(|e| {
+                        !(ends_in_interpolation && matches!(e, ParserError::UnexpectedEof(_)))
+                    })
+                    .map(ToString::to_string),
+            )
+            .collect();
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+
+        let descriptor = SfcDescriptor::from_root(root).map_err(|e| vec![e.to_string()])?;
+
+        // Only the template is condensed, the other blocks are raw text
+        let mut template = descriptor.template.unwrap();
+        condense_whitespace(&mut template.root, self.options.whitespace);
+
+        let script: String = [&descriptor.script, &descriptor.script_setup]
+            .into_iter()
+            .flatten()
+            .filter(|block| is_rust(block))
+            .map(|block| block.content.as_str())
+            .collect();
+
+        let scope_id = style::scope_id(&path);
+        let is_scoped = descriptor
+            .styles
+            .iter()
+            .any(|block| block.attribute("scoped").is_some());
+        let (styles, modules) = styles(&descriptor.styles, &path, &scope_id)?;
+
+        let mut generator = Generator {
+            directives: self.options.directives.clone(),
+            code: String::new(),
+            path: path.clone(),
+            scope_attribute: is_scoped.then(|| style::scope_attribute(&scope_id)),
+            components: Vec::new(),
+            dynamic: Vec::new(),
+            modules,
+            static_classes: Vec::new(),
+            once: false,
+            memos: Vec::new(),
+            memo_count: 0,
+            transition: None,
+            refs: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        for child in &template.root.children {
+            generator.convert_children(child);
+        }
+        if !generator.diagnostics.is_empty() {
+            return Err(generator.diagnostics);
+        }
+        // Components named in the script or in `:is`, which can be rendered dynamically
+        let mut values = String::new();
+        let referenced = std::iter::once(script.as_str())
+            .chain(generator.dynamic.iter().map(String::as_str))
+            .flat_map(pascal_case_identifiers);
+        for name in referenced {
+            let path = resolve_component(&path, &name);
+            let declared = format!("const {name}:");
+            if values.contains(&declared) || !Path::new(&path).is_file() {
+                continue;
+            }
+
+            values.push_str(&format!(
+                "
+            #[allow(dead_code, non_upper_case_globals)]
+            const {name}: villain_runtime::Component = villain_runtime::Component::new({name:?}, {});",
+                render_function(&path)
+            ));
+            if !generator.components.iter().any(|(_, used)| *used == path) {
+                generator.components.push((name, path));
+            }
+        }
+
+        let code = generator.code;
+        let modules: String = generator
+            .modules
+            .iter()
+            .enumerate()
+            .map(|(index, module)| module.code(index, &scope_id))
+            .collect();
+
+        let refs: String = generator
+            .refs
+            .iter()
+            .map(|(name, ty)| {
+                format!(
+                    "
+            let {name}: {ty} = Default::default();
+            let template_ref_{name} = {name}.clone();"
+                )
+            })
+            .collect();
+        let setup = format!(
+            "
+            #[allow(unused_variables)]
+            let msg = Mutable::new(1);
+            {values}
+            {modules}
+            {refs}
+            {script}"
+        );
+        let render = format!(
+            "
+            // Stack of parents since nodes as nested and we basically emulate recursion
+            let mut parents = vec![root];
+            {code}"
+        );
+        // Both run in closures returning results, so the script can use `?` and
+        // errors reach the error boundaries. An async script runs in a future,
+        // which returns the rendering of the template once it has finished.
+        let body = if is_async(&script) {
+            format!(
+                "
+            villain_runtime::render_async(document, &root, async move {{
+                {setup}
+                Ok::<_, villain_runtime::Error>(
+                    move |document: &web_sys::Document, root: web_sys::Element| -> Result<(), villain_runtime::Error> {{
+                        {render}
+                        Ok(())
+                    }}
+                )
+            }});"
+            )
+        } else {
+            format!(
+                "
+            villain_runtime::setup(move || -> Result<(), villain_runtime::Error> {{
+                {setup}
+                {render}
+                Ok(())
+            }});"
+            )
+        };
+
+        let component = &mut self.components[index];
+        component.styles = styles;
+        component.code = format!(
+            "
+        fn {function}(document: &web_sys::Document, root: web_sys::Element) {{
+            // Hooks registered by the script belong to this instance
+            let _instance = villain_runtime::Instance::enter();
+
+            // Available to the script, which may not need all of them
+            #[allow(unused_imports)]
+            use futures_signals::signal::{{Mutable, SignalExt}};
+            #[allow(unused_imports)]
+            use wasm_bindgen_futures::spawn_local;
+            {body}
+        }}",
+            function = component.function,
+        );
+
+        Ok(generator.components)
+    }
+
+    /// The styles of all components in the order they are first used. Styles that
+    /// appear more than once, e.g. a shared `src` file, are only kept the first time.
+    fn bundle(&self) -> String {
+        let mut bundled: Vec<&str> = Vec::new();
+        for css in self.components.iter().flat_map(|c| &c.styles) {
+            if !bundled.contains(&css.as_str()) {
+                bundled.push(css);
+            }
+        }
+
+        bundled.concat()
+    }
+}
+
+/// The classes of a `<style module>` block, accessible in the template through
+/// a variable named like the module, `style` by default
+struct CssModule {
+    name: String,
+    classes: Vec<String>,
+}
+
+impl CssModule {
+    /// The field of a class, e.g. `text_danger` for `.text-danger`, `_2col` for
+    /// `.2col` and `r#type` for `.type`
+    fn field(class: &str) -> String {
+        let mut field: String = class
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        if field.starts_with(|c: char| c.is_numeric()) {
+            field.insert(0, '_');
+        }
+
+        if syn::parse_str::<syn::Ident>(&field).is_ok() {
+            field
+        } else if syn::parse_str::<syn::Ident>(&format!("r#{field}")).is_ok() {
+            format!("r#{field}")
+        } else {
+            // Keywords such as `self` that cannot be raw identifiers
+            format!("{field}_")
+        }
+    }
+
+    /// Reports classes that map to the same field, e.g. `.a-b` and `.a_b`
+    fn check_fields(&self) -> Vec<String> {
+        let mut diagnostics = Vec::new();
+        for (index, class) in self.classes.iter().enumerate() {
+            let field = Self::field(class);
+            if let Some(other) = self.classes[..index]
+                .iter()
+                .find(|other| Self::field(other) == field)
+            {
+                diagnostics.push(format!(
+                    "Classes `.{other}` and `.{class}` of <style module> `{}` both map to the field `{field}`, rename one of them",
+                    self.name
+                ));
+            }
+        }
+
+        diagnostics
+    }
+
+    /// A struct with a field per class holding its renamed class, and the
+    /// variable the template accesses it through
+    fn code(&self, index: usize, scope_id: &str) -> String {
+        let (fields, values): (String, String) = self
+            .classes
+            .iter()
+            .map(|class| {
+                let field = Self::field(class);
+                (
+                    format!("{field}: &'static str,"),
+                    format!("{field}: \"{class}_{scope_id}\","),
+                )
+            })
+            .unzip();
+
+        format!(
+            "
+            #[allow(dead_code)]
+            struct CssModule{index} {{ {fields} }}
+            let {name} = CssModule{index} {{ {values} }};",
+            name = self.name
+        )
+    }
+}
+
+/// Returns the CSS of each `<style>` block, scoping the ones marked `scoped` and
+/// renaming the classes of the ones marked `module`
+fn styles(
+    styles: &[SfcBlock],
+    path: &str,
+    scope_id: &str,
+) -> Result<(Vec<String>, Vec<CssModule>), Vec<String>> {
+    let mut stylesheets = Vec::new();
+    let mut modules: Vec<CssModule> = Vec::new();
+
+    for block in styles {
+        if let Some(lang) = block.lang().filter(|lang| *lang != "css") {
+            return Err(vec![format!("Unsupported style language: {lang}")]);
+        }
+
+        let css = match block.attribute("src") {
+            Some(src) => {
+                let src = Path::new(path).parent().unwrap_or(Path::new("")).join(src);
+                std::fs::read_to_string(&src).map_err(|e| {
+                    vec![format!("Could not read style file {}: {e}", src.display())]
+                })?
+            }
+            None => block.content.clone(),
+        };
+
+        let css = match block.attribute("module") {
+            Some(name) => {
+                let name = if name.is_empty() { "style" } else { name };
+                if syn::parse_str::<syn::Ident>(name).is_err() {
+                    return Err(vec![format!("Invalid style module name: {name}")]);
+                }
+
+                let (css, classes) = style::module_css(&css, scope_id);
+                // Blocks sharing a module name are merged into one accessor
+                match modules.iter_mut().find(|module| module.name == name) {
+                    Some(module) => {
+                        for class in classes {
+                            if !module.classes.contains(&class) {
+                                module.classes.push(class);
+                            }
+                        }
+                    }
+                    None => modules.push(CssModule {
+                        name: name.to_string(),
+                        classes,
+                    }),
+                }
+
+                css
+            }
+            None => css,
+        };
+
+        if block.attribute("scoped").is_some() {
+            stylesheets.push(style::scope_css(&css, scope_id).map_err(|e| vec![e])?);
+        } else {
+            stylesheets.push(css);
+        }
+    }
+
+    let collisions: Vec<String> = modules.iter().flat_map(CssModule::check_fields).collect();
+    if !collisions.is_empty() {
+        return Err(collisions);
+    }
+
+    Ok((stylesheets, modules))
+}
+
+/// The code of an object key, literal keys are passed through `literal` first
+fn key_code(key: Key, literal: impl Fn(&str) -> String) -> String {
+    match key {
+        Key::Literal(name) => format!("{:?}", literal(&name)),
+        Key::Computed(expression) => format!("&ToString::to_string(&({expression}))"),
+    }
+}
+
+/// Generates the code that builds the DOM of a template
+struct Generator {
+    /// Registered custom directives and the types implementing them
+    directives: Vec<(String, String)>,
+    code: String,
+    /// Path of the component the template belongs to
+    path: String,
+    /// Attribute added to every element if the component has scoped styles
+    scope_attribute: Option<String>,
+    /// Tags and paths of the components used by the template
+    components: Vec<(String, String)>,
+    /// The expressions of `:is` bindings, which may name components
+    dynamic: Vec<String>,
+    /// The `<style module>` blocks of the component
+    modules: Vec<CssModule>,
+    /// Classes of the `class` attribute of the current element, which bound
+    /// classes never remove
+    static_classes: Vec<String>,
+    /// Whether the current subtree has `v-once` and is rendered without updates
+    once: bool,
+    /// Variables of the `v-memo`s the current subtree is in
+    memos: Vec<String>,
+    /// Number of `v-memo`s so far, to name their variables
+    memo_count: usize,
+    /// The `<Transition>` the current element or component is the child of
+    transition: Option<String>,
+    /// Names and handle types of the `ref`s in the template
+    refs: Vec<(String, String)>,
+    diagnostics: Vec<String>,
+}
+
+impl Generator {
+    /// The argument passing the Rust expression `value` to a runtime binding.
+    /// Inside `v-once` only its first value is used, inside `v-memo` it only
+    /// changes with the dependencies of the memo.
+    fn reactive(&self, value: &str) -> String {
+        let mut reactive = format!("&({value})");
+        for memo in &self.memos {
+            reactive = format!("&villain_runtime::Memoized({reactive}, &{memo})");
+        }
+
+        if self.once {
+            format!("&villain_runtime::Once({reactive})")
+        } else {
+            reactive
+        }
+    }
+
+    /// Creates the memo of a `v-memo="[a, b]"`, which gates all bindings of the
+    /// element and its subtree
+    fn memo(&mut self, value: Option<&str>) {
+        let dependencies = match value.map(|value| (value, parse_binding(value))) {
+            Some((value, Ok(parts))) if enclosed(value.trim(), '[', ']').is_some() => parts,
+            _ => {
+                self.diagnostics.push(
+                    "v-memo requires an array of dependencies, e.g. v-memo=\"[a, b]\"".to_string(),
+                );
+                return;
+            }
+        };
+
+        let memo = format!("memo_{}", self.memo_count);
+        self.memo_count += 1;
+        self.code.push_str(&format!(
+            "
+                    let {memo} = villain_runtime::Memo::new();"
+        ));
+        for dependency in dependencies {
+            let BindingPart::Expression(dependency) = dependency else {
+                self.diagnostics
+                    .push("v-memo dependencies must be expressions".to_string());
+                continue;
+            };
+            self.code.push_str(&format!(
+                "
+                    {memo}.depend(&({dependency}));"
+            ));
+        }
+        self.memos.push(memo);
+    }
+
+    /// Binds a registered custom directive, `v-click-outside` is registered as `click_outside`
+    fn custom_directive(&mut self, directive: &Directive) {
+        let name = directive.name.replace('-', "_");
+        let Some((_, path)) = self
+            .directives
+            .iter()
+            .find(|(registered, _)| *registered == name)
+        else {
+            self.diagnostics.push(format!(
+                "Unknown directive v-{}, register it with `directives = {{ {name}: Type }}`",
+                directive.name
+            ));
+            return;
+        };
+
+        let arg = match &directive.arg {
+            Some(arg) if directive.dynamic_arg => format!("Some(ToString::to_string(&({arg})))"),
+            Some(arg) => format!("Some({arg:?}.to_string())"),
+            None => "None".to_string(),
+        };
+        let value = self.reactive(directive.value.as_deref().unwrap_or("()"));
+
+        self.code.push_str(&format!(
+            "
+                    villain_runtime::bind_directive::<{path}, _>(parents.last().unwrap(), {arg}, &{:?}, {value});",
+            directive.modifiers
+        ));
+    }
+
+    /// Converts `v-bind:name`, `:[name]`, `:name.camel` and `:name.prop` or `.name`,
+    /// which sets the DOM property instead of the attribute
+    fn bind_directive(&mut self, directive: &Directive) {
+        let Some(arg) = &directive.arg else {
+            self.diagnostics.push(
+                "v-bind without an argument is not supported yet, bind each attribute with v-bind:name"
+                    .to_string(),
+            );
+            return;
+        };
+        let Some(value) = &directive.value else {
+            self.diagnostics
+                .push(format!("v-bind:{arg} requires an expression"));
+            return;
+        };
+
+        let has = |modifier: &str| directive.modifiers.iter().any(|m| m == modifier);
+        if let Some(modifier) = directive
+            .modifiers
+            .iter()
+            .find(|m| !["prop", "attr", "camel"].contains(&m.as_str()))
+        {
+            self.diagnostics
+                .push(format!("Unknown modifier .{modifier} on v-bind:{arg}"));
+            return;
+        }
+        if has("prop") && has("attr") {
+            self.diagnostics
+                .push(format!("v-bind:{arg} cannot have both .prop and .attr"));
+            return;
+        }
+        self.check_module_classes(value);
+
+        let name = if directive.dynamic_arg {
+            if has("camel") {
+                self.diagnostics.push(format!(
+                    "The .camel modifier needs a static name, v-bind:[{arg}] is only known at runtime"
+                ));
+                return;
+            }
+            format!("&ToString::to_string(&({arg}))")
+        } else if has("camel") {
+            format!("{:?}", camel_case(arg))
+        } else if has("prop") {
+            format!("{arg:?}")
+        } else {
+            self.bind(arg, value);
+            return;
+        };
+
+        let function = if has("prop") {
+            "bind_property"
+        } else {
+            "bind_attribute"
+        };
+        self.code.push_str(&format!(
+            "
+                    villain_runtime::{function}(parents.last().unwrap(), {name}, {});",
+            self.reactive(value)
+        ));
+    }
+
+    /// Binds the attribute `name` to the Rust expression `value`. Like in Vue,
+    /// `class` and `style` are merged with their static values and also accept
+    /// objects and arrays.
+    fn bind(&mut self, name: &str, value: &str) {
+        if name != "class" && name != "style" {
+            self.code.push_str(&format!(
+                "
+                    villain_runtime::bind_attribute(parents.last().unwrap(), {name:?}, {});",
+                self.reactive(value)
+            ));
+            return;
+        }
+
+        let parts = match parse_binding(value) {
+            Ok(parts) => parts,
+            Err(e) => {
+                self.diagnostics.push(e);
+                return;
+            }
+        };
+
+        for part in parts {
+            let code = match (name, part) {
+                ("class", BindingPart::Entry { key, value }) => format!(
+                    "villain_runtime::bind_class_toggle(parents.last().unwrap(), {}, {});",
+                    key_code(key, |class| class.to_string()),
+                    self.reactive(&value)
+                ),
+                ("class", BindingPart::Expression(value)) => format!(
+                    "villain_runtime::bind_class_list(parents.last().unwrap(), &{:?}, {});",
+                    self.static_classes,
+                    self.reactive(&value)
+                ),
+                (_, BindingPart::Entry { key, value }) => format!(
+                    "villain_runtime::bind_style_property(parents.last().unwrap(), {}, {});",
+                    key_code(key, css_property),
+                    self.reactive(&value)
+                ),
+                (_, BindingPart::Expression(value)) => format!(
+                    "villain_runtime::bind_style_text(parents.last().unwrap(), {});",
+                    self.reactive(&value)
+                ),
+            };
+
+            self.code.push_str("\n                    ");
+            self.code.push_str(&code);
+        }
+    }
+
+    /// Reports accesses such as `style.missing` to classes a module does not define,
+    /// which would otherwise only fail as an unknown field in the generated code
+    fn check_module_classes(&mut self, expression: &str) {
+        for module in &self.modules {
+            let access = format!("{}.", module.name);
+            for (start, _) in expression.match_indices(&access) {
+                let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+                if expression[..start].ends_with(is_ident) {
+                    continue;
+                }
+
+                // Including the `#` of raw identifiers such as `style.r#type`
+                let field: String = expression[start + access.len()..]
+                    .chars()
+                    .take_while(|c| is_ident(*c) || *c == '#')
+                    .collect();
+                if !module
+                    .classes
+                    .iter()
+                    .any(|class| CssModule::field(class) == field)
+                {
+                    self.diagnostics.push(format!(
+                        "Class `{field}` is not defined in <style module> `{}`",
+                        module.name
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Converts `<component :is>`, passing `keep_alive` on to `bind_component`
+    fn dynamic_component(&mut self, node: &Node, keep_alive: &str) {
+        let is = node
+            .children
+            .iter()
+            .find_map(|child| match &child.node_type {
+                NodeType::Directive(d) if d.name == "bind" && d.arg.as_deref() == Some("is") => {
+                    d.value.clone().map(|value| (value, true))
+                }
+                NodeType::Attribute(name, Some(value)) if name == "is" => {
+                    value.value.clone().map(|value| (value, false))
+                }
+                _ => None,
+            });
+
+        match is {
+            Some((value, true)) => {
+                let transition = self.transition_argument();
+                self.code.push_str(&format!(
+                    "
+                    villain_runtime::bind_component(document, parents.last().unwrap(), {}, {keep_alive}, {transition});",
+                    self.reactive(&value)
+                ));
+                self.dynamic.push(value);
+            }
+            // A static `is` names the component like its tag would
+            Some((name, false)) if is_component(&name) => self.convert_children(&Node {
+                node_type: NodeType::Tag(name),
+                children: Vec::new(),
+                span: node.span.clone(),
+            }),
+            _ => self.diagnostics.push(
+                "<component> requires an `:is` binding or a component name in `is`".to_string(),
+            ),
+        }
+    }
+
+    /// Converts `<KeepAlive>` around a single `<component :is>`. `include` and
+    /// `exclude` are comma separated component names, `max` a number.
+    fn keep_alive(&mut self, node: &Node) {
+        let names = |attribute: &str| {
+            let value = node
+                .children
+                .iter()
+                .find_map(|child| match &child.node_type {
+                    NodeType::Attribute(name, Some(value)) if name == attribute => {
+                        value.value.clone()
+                    }
+                    _ => None,
+                });
+            let names: Vec<String> = value
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(|name| format!("{name:?}"))
+                .collect();
+
+            format!("&[{}]", names.join(", "))
+        };
+        let include = names("include");
+        let exclude = names("exclude");
+
+        let mut max = "None".to_string();
+        for child in &node.children {
+            match &child.node_type {
+                NodeType::Attribute(name, Some(value)) if name == "max" => {
+                    match value.value.as_deref().map(str::parse::<usize>) {
+                        Some(Ok(value)) => max = format!("Some({value})"),
+                        _ => self
+                            .diagnostics
+                            .push("`max` of <KeepAlive> must be a number".to_string()),
+                    }
+                }
+                NodeType::Directive(d) if d.name == "bind" && d.arg.as_deref() == Some("max") => {
+                    if let Some(value) = &d.value {
+                        max = format!("Some({value})");
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        match content(node).as_slice() {
+            [child] if matches!(&child.node_type, NodeType::Tag(tag) if tag == "component") => {
+                let keep_alive = format!(
+                    "Some(villain_runtime::KeepAlive {{ include: {include}, exclude: {exclude}, max: {max} }})"
+                );
+                self.dynamic_component(child, &keep_alive);
+            }
+            _ => self
+                .diagnostics
+                .push("<KeepAlive> requires a single <component :is> child".to_string()),
+        }
+    }
+
+    /// Converts `<Suspense>` and `<ErrorBoundary>`. Their content and `#fallback`
+    /// template render into containers, and the boundary shows one of them: the
+    /// fallback until the async components of the content have resolved, or once
+    /// the content reported an error. `#fallback="error"` binds the error message.
+    fn boundary(&mut self, node: &Node, tag: &str) {
+        let fallback_slot = |child: &Node| {
+            if !matches!(&child.node_type, NodeType::Tag(tag) if tag == "template") {
+                return None;
+            }
+            child
+                .children
+                .iter()
+                .find_map(|attribute| match &attribute.node_type {
+                    NodeType::Directive(d)
+                        if d.name == "slot" && d.arg.as_deref() == Some("fallback") =>
+                    {
+                        Some(d.value.clone())
+                    }
+                    _ => None,
+                })
+        };
+        let (fallback, content): (Vec<&Node>, Vec<&Node>) = content(node)
+            .into_iter()
+            .partition(|child| fallback_slot(child).is_some());
+        if fallback.len() > 1 {
+            self.diagnostics
+                .push(format!("<{tag}> has more than one #fallback"));
+        }
+
+        let mut error = None;
+        if let Some(prop) = fallback.first().copied().and_then(fallback_slot).flatten() {
+            // Like the destructured slot props in Vue, `{ error }` works as well
+            let name = prop
+                .trim()
+                .trim_start_matches('{')
+                .trim_end_matches('}')
+                .trim();
+            if tag == "ErrorBoundary" && is_identifier(name) {
+                error = Some(name.to_string());
+            } else {
+                self.diagnostics.push(format!(
+                    "#fallback of <{tag}> can only bind the error of an <ErrorBoundary>, not `{prop}`"
+                ));
+            }
+        }
+
+        self.code.push_str(&format!(
+            "
+                    let boundary = villain_runtime::{tag}::new(document, parents.last().unwrap());
+                    {{
+                    let _boundary = boundary.enter();
+                    parents.push(boundary.content());"
+        ));
+        for child in content {
+            self.convert_children(child);
+        }
+        self.code.push_str(
+            "
+                    parents.pop();
+                    }
+                    {",
+        );
+        if let Some(error) = error {
+            self.code.push_str(&format!(
+                "
+                    #[allow(unused_variables)]
+                    let {error} = boundary.error();"
+            ));
+        }
+        self.code.push_str(
+            "
+                    parents.push(boundary.fallback());",
+        );
+        // Only the content of the `<template #fallback>`, its `v-slot` is handled here
+        for child in fallback.iter().flat_map(|template| &template.children) {
+            if !matches!(&child.node_type, NodeType::Directive(d) if d.name == "slot") {
+                self.convert_children(child);
+            }
+        }
+        self.code.push_str(
+            "
+                    parents.pop();
+                    }
+                    boundary.mount();",
+        );
+    }
+
+    /// Converts `<Transition name>` around a single element, whose `v-show` plays
+    /// the transition, or a dynamic component
+    fn transition(&mut self, node: &Node) {
+        let name = node
+            .children
+            .iter()
+            .find_map(|child| match &child.node_type {
+                NodeType::Attribute(name, Some(value)) if name == "name" => value.value.clone(),
+                _ => None,
+            })
+            .unwrap_or_else(|| "v".to_string());
+
+        // Only these children have bindings that play the transition
+        let is_supported = |child: &Node| match &child.node_type {
+            NodeType::Tag(tag) if tag == "component" || tag == "KeepAlive" => true,
+            NodeType::Tag(tag) if !is_component(tag) => child.children.iter().any(
+                |child| matches!(&child.node_type, NodeType::Directive(d) if d.name == "show"),
+            ),
+            _ => false,
+        };
+
+        match content(node).as_slice() {
+            [child] if is_supported(child) => {
+                self.transition = Some(format!("villain_runtime::Transition::new({name:?})"));
+                self.convert_children(child);
+                self.transition = None;
+            }
+            _ => self.diagnostics.push(
+                "<Transition> requires a single element with v-show or a <component :is>, \
+                 optionally inside <KeepAlive>"
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// The `transition` argument of a runtime binding, which only the direct child
+    /// of a `<Transition>` gets
+    fn transition_argument(&mut self) -> String {
+        match self.transition.take() {
+            Some(transition) => format!("Some({transition})"),
+            None => "None".to_string(),
+        }
+    }
+
+    /// Converts `<Teleport>`, whose content renders into a container that is then
+    /// moved to the target. `to` is a selector, `disabled` keeps the content in place.
+    fn teleport(&mut self, node: &Node) {
+        let mut to = None;
+        let mut disabled = "false".to_string();
+        for child in &node.children {
+            match &child.node_type {
+                NodeType::Attribute(name, Some(value)) if name == "to" => {
+                    to = value.value.as_ref().map(|value| format!("{value:?}"));
+                }
+                NodeType::Attribute(name, _) if name == "disabled" => disabled = "true".to_string(),
+                NodeType::Directive(d) if d.name == "bind" => match (d.arg.as_deref(), &d.value) {
+                    (Some("to"), Some(value)) => to = Some(format!("&({value})")),
+                    (Some("disabled"), Some(value)) => disabled = value.clone(),
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        let Some(to) = to else {
+            self.diagnostics
+                .push("<Teleport> requires a `to` selector".to_string());
+            return;
+        };
+
+        self.code.push_str(&format!(
+            "
+                    let teleport = villain_runtime::Teleport::new(document, parents.last().unwrap(), {to});
+                    parents.push(teleport.container());"
+        ));
+        for child in node.children.iter().filter(|child| !child.is_attribute()) {
+            self.convert_children(child);
+        }
+        self.code.push_str(&format!(
+            "
+                    parents.pop();
+                    teleport.mount({});",
+            self.reactive(&disabled)
+        ));
+    }
+
+    /// Sets the handle of the `ref` on `node` to the element or component just
+    /// rendered. The handles are declared before the script, which uses them.
+    fn template_ref(&mut self, node: &Node, tag: &str) {
+        let Some(name) = node
+            .children
+            .iter()
+            .find_map(|child| match &child.node_type {
+                NodeType::Attribute(name, Some(value)) if name == "ref" => value.value.clone(),
+                _ => None,
+            })
+        else {
+            return;
+        };
+        if !is_identifier(&name) {
+            self.diagnostics
+                .push(format!("ref `{name}` is not a valid Rust identifier"));
+            return;
+        }
 
-    let root: Node = parser.try_into().unwrap();
+        let ty = ref_type(tag);
+        match self.refs.iter().find(|(declared, _)| *declared == name) {
+            Some((_, declared)) if *declared != ty => self.diagnostics.push(format!(
+                "ref `{name}` is used for elements of different types, {declared} and {ty}"
+            )),
+            Some(_) => {}
+            None => self.refs.push((name.clone(), ty)),
+        }
+
+        // The script may move the handle into closures, so the template sets a clone
+        if is_component(tag) {
+            self.code.push_str(&format!(
+                "
+                    template_ref_{name}.set_last_child();"
+            ));
+        } else {
+            self.code.push_str(&format!(
+                "
+                    template_ref_{name}.set(parents.last().unwrap());"
+            ));
+        }
+    }
 
     /// Converts the children of a node into code
-    fn convert_children(code: &mut String, node: &Node) {
+    fn convert_children(&mut self, node: &Node) {
         match &node.node_type {
+            NodeType::Tag(tag) if tag == "component" => self.dynamic_component(node, "None"),
+            NodeType::Tag(tag) if tag == "KeepAlive" => self.keep_alive(node),
+            NodeType::Tag(tag) if tag == "Teleport" => self.teleport(node),
+            NodeType::Tag(tag) if tag == "Transition" => self.transition(node),
+            NodeType::Tag(tag) if tag == "Suspense" || tag == "ErrorBoundary" => {
+                self.boundary(node, tag)
+            }
+            NodeType::Tag(tag) if tag == "TransitionGroup" => self.diagnostics.push(
+                "<TransitionGroup> animates the items of v-for, which templates do not support yet"
+                    .to_string(),
+            ),
+            NodeType::Tag(tag) if is_component(tag) => {
+                let path = resolve_component(&self.path, tag);
+                match &self.scope_attribute {
+                    // The root elements of the child carry the scope of this
+                    // component as well, so its styles can reach them
+                    Some(scope_attribute) => self.code.push_str(&format!(
+                        "
+                    let before = parents.last().unwrap().last_element_child();
+                    {}(document, parents.last().unwrap().clone());
+                    let mut root = match before {{
+                        Some(before) => before.next_element_sibling(),
+                        None => parents.last().unwrap().first_element_child(),
+                    }};
+                    while let Some(e) = root {{
+                        e.set_attribute(\"{scope_attribute}\", \"\").unwrap();
+                        root = e.next_element_sibling();
+                    }}",
+                        render_function(&path)
+                    )),
+                    None => self.code.push_str(&format!(
+                        "
+                    {}(document, parents.last().unwrap().clone());",
+                        render_function(&path)
+                    )),
+                }
+                self.template_ref(node, tag);
+
+                if !self.components.iter().any(|(_, used)| *used == path) {
+                    self.components.push((tag.clone(), path));
+                }
+            }
             NodeType::Tag(tag) => {
-                if tag != "template" {
-                    code.push_str(
+                let has_directive = |name: &str| {
+                    node.children.iter().any(|child| {
+                        matches!(&child.node_type, NodeType::Directive(d) if d.name == name)
+                    })
+                };
+                let has_content = node.children.iter().any(|child| !child.is_attribute());
+                for replacing in ["html", "text"] {
+                    if has_directive(replacing) && has_content {
+                        self.diagnostics.push(format!(
+                            "v-{replacing} on <{tag}> would overwrite its children, remove either"
+                        ));
+                    }
+                }
+                let once = self.once;
+                self.once |= has_directive("once");
+                let memos = self.memos.len();
+                let memo = node
+                    .children
+                    .iter()
+                    .find_map(|child| match &child.node_type {
+                        NodeType::Directive(d) if d.name == "memo" => Some(d.value.as_deref()),
+                        _ => None,
+                    });
+
+                // Attributes come before the content, so these belong to this tag
+                // until its children are converted
+                self.static_classes = node
+                    .children
+                    .iter()
+                    .find_map(|child| match &child.node_type {
+                        NodeType::Attribute(name, Some(value)) if name == "class" => {
+                            value.value.clone()
+                        }
+                        _ => None,
+                    })
+                    .map(|classes| classes.split_whitespace().map(str::to_string).collect())
+                    .unwrap_or_default();
+
+                // A nested `<template>` only groups its children and creates no element
+                let is_fragment = tag == "template";
+                if !is_fragment {
+                    self.code.push_str(
                         format!(
                             "
                     let e = document.create_element(\"{tag}\").unwrap();
@@ -40,49 +1382,138 @@ pub fn expand_template(path: String) -> TokenStream {
                         )
                         .as_str(),
                     );
+
+                    if let Some(scope_attribute) = &self.scope_attribute {
+                        self.code.push_str(&format!(
+                            "parents.last().unwrap().set_attribute(\"{scope_attribute}\", \"\").unwrap();"
+                        ));
+                    }
+                    self.template_ref(node, tag);
                 }
 
+                // Before the attributes, so the bindings of the element are gated too
+                if let Some(memo) = memo {
+                    self.memo(memo);
+                }
+
+                // Only the `v-show` of the element itself plays the transition
+                let transition = self.transition.take();
                 for child in &node.children {
-                    convert_children(code, child);
+                    self.transition = transition.clone().filter(|_| child.is_attribute());
+                    self.convert_children(child);
+                }
+                self.transition = None;
+
+                // Queued after the bindings of the subtree, so it stays hidden by
+                // `[v-cloak] { display: none }` until their first values are applied
+                if has_directive("cloak") {
+                    self.code
+                        .push_str("villain_runtime::uncloak(parents.last().unwrap());");
                 }
 
-                code.push_str("parents.pop();");
+                if !is_fragment {
+                    self.code.push_str("parents.pop();");
+                }
+                self.once = once;
+                self.memos.truncate(memos);
             }
-            NodeType::Attribute(name, value, _) => {
-                if name == "v-model" {
-                    let sig = value.as_ref().unwrap().value.as_ref().unwrap();
+            NodeType::Directive(directive) if directive.name == "model" => {
+                let sig = directive.value.as_ref().unwrap();
 
-                    code.push_str(
-                        format!(
-                            r#"
+                self.code.push_str(
+                    format!(
+                        r#"
     let cloned_{sig} = msg.clone();
 
-    parents
-        .last()
-        .unwrap()
-        .add_event_listener_with_callback(
-            "keypress",
-            &Closure::<dyn FnMut(web_sys::Event)>::new(move |event: web_sys::Event| {{
-                let input = event
-                    .current_target()
-                    .unwrap()
-                    .dyn_into::<web_sys::HtmlInputElement>()
-                    .unwrap();
-
-                cloned_{sig}.set(input.value().parse::<i32>().unwrap());
-            }})
-            .into_js_value()
-            .as_ref()
-            .unchecked_ref(),
-        )
-        .unwrap();
+    villain_runtime::listen(
+        parents.last().unwrap(),
+        "keypress",
+        move |event: web_sys::Event| {{
+            let input = event
+                .current_target()
+                .unwrap()
+                .dyn_into::<web_sys::HtmlInputElement>()
+                .unwrap();
+
+            cloned_{sig}.set(input.value().parse::<i32>()?);
+            Ok(())
+        }},
+    );
                         "#,
-                        )
-                        .as_str(),
-                    );
-                }
+                    )
+                    .as_str(),
+                );
+            }
+            NodeType::Directive(directive) if directive.name == "bind" => {
+                self.bind_directive(directive);
+            }
+            NodeType::Directive(directive) if directive.name == "html" => {
+                let Some(value) = &directive.value else {
+                    self.diagnostics
+                        .push("v-html requires an expression".to_string());
+                    return;
+                };
+
+                self.code.push_str(&format!(
+                    "
+                    villain_runtime::bind_html(parents.last().unwrap(), {});",
+                    self.reactive(value)
+                ));
+            }
+            NodeType::Directive(directive) if directive.name == "text" => {
+                let Some(value) = &directive.value else {
+                    self.diagnostics
+                        .push("v-text requires an expression".to_string());
+                    return;
+                };
+
+                self.code.push_str(&format!(
+                    "
+                    villain_runtime::bind_text(parents.last().unwrap(), {});",
+                    self.reactive(value)
+                ));
+            }
+            NodeType::Directive(directive) if directive.name == "cloak" => {
+                self.code.push_str(
+                    "
+                    parents.last().unwrap().set_attribute(\"v-cloak\", \"\").unwrap();",
+                );
+            }
+            NodeType::Directive(directive) if directive.name == "show" => {
+                let Some(value) = &directive.value else {
+                    self.diagnostics
+                        .push("v-show requires an expression".to_string());
+                    return;
+                };
 
-                code.push_str(
+                let transition = self.transition_argument();
+                self.code.push_str(&format!(
+                    "
+                    villain_runtime::bind_show(parents.last().unwrap(), {}, {transition});",
+                    self.reactive(value)
+                ));
+            }
+            NodeType::Directive(directive) if directive.name == "slot" => {
+                self.diagnostics.push(
+                    "v-slot is not supported yet, except for the #fallback of <Suspense> and <ErrorBoundary>"
+                        .to_string(),
+                );
+            }
+            NodeType::Directive(directive)
+                if UNSUPPORTED_DIRECTIVES.contains(&directive.name.as_str()) =>
+            {
+                self.diagnostics
+                    .push(format!("v-{} is not supported yet", directive.name));
+            }
+            NodeType::Directive(directive)
+                if !BUILTIN_DIRECTIVES.contains(&directive.name.as_str()) =>
+            {
+                self.custom_directive(directive);
+            }
+            // Handled by `template_ref`
+            NodeType::Attribute(name, _) if name == "ref" => {}
+            NodeType::Attribute(name, value) => {
+                self.code.push_str(
                     format!(
                         "
                      parents.last().unwrap().set_attribute(\"{}\", \"{}\").unwrap();",
@@ -97,52 +1528,674 @@ pub fn expand_template(path: String) -> TokenStream {
                 );
             }
             NodeType::Text(text) => {
-                code.push_str(
-                    format!(
-                        "
-                    let e = document.create_text_node(\"{}\");
-                    let p = parents.last().unwrap().clone();
-                    p.append_child(&e).unwrap();
+                self.code.push_str(&format!(
+                    "
+                    parents.last().unwrap().append_child(&document.create_text_node({text:?})).unwrap();"
+                ));
+            }
+            NodeType::Code(expression) => {
+                self.check_module_classes(expression);
+                self.code.push_str(&format!(
+                    "
+                    villain_runtime::bind_text_node(document, parents.last().unwrap(), {});",
+                    self.reactive(expression)
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                    let document_clone = document.clone();
-                    let future = msg.signal().for_each(move |value| {{
-                        // This code is run for the current value of my_state,
-                        // and also every time my_state changes
+    /// Compiles `source` and checks that the result is valid Rust tokens
+    fn compile_source(source: &str) -> String {
+        let options = CompileOptions::default();
+        let code = compile(source.to_string(), &options).unwrap();
+        code.parse::<proc_macro2::TokenStream>().unwrap();
 
+        code
+    }
 
-                        let n = document_clone.create_text_node(&format!(\"{{}}\", value));
-                        p.append_child(&n).unwrap();
-                        // p.remove_child(&e).unwrap();
+    #[test]
+    fn test_adds_scope_attribute_to_elements() {
+        let code = compile_source(
+            "<template><p><b>Hi</b></p></template><style scoped>b { color: red; }</style>",
+        );
+        let attribute = style::scope_attribute(&style::scope_id("src/App.vue"));
 
-                        async {{}}
-                    }});
-                    spawn_local(future);
-                    ",
-                        &text.escape_default()
-                    )
-                    .as_str(),
-                );
-            }
-            _ => {}
+        assert_eq!(
+            code.matches(&format!("set_attribute(\"{attribute}\", \"\")"))
+                .count(),
+            2
+        );
+        assert!(code.contains(&format!("b[{attribute}] {{ color: red; }}")));
+        assert!(code.contains("document.head()"));
+    }
+
+    #[test]
+    fn test_scopes_root_elements_of_child_components() {
+        let (path, code) = compile_fixture("scoped_child");
+        let attribute = style::scope_attribute(&style::scope_id(&path));
+
+        let child = code.find("last_element_child()").unwrap();
+        assert!(code[child..].contains(&format!("e.set_attribute(\"{attribute}\", \"\")")));
+        // The child itself has no scoped styles
+        assert_eq!(code.matches("set_attribute(\"data-v-").count(), 2);
+    }
+
+    #[test]
+    fn test_rejects_slotted_selectors() {
+        let options = CompileOptions::default();
+        let result = compile(
+            "<template><p></p></template><style scoped>:slotted(p) { }</style>".to_string(),
+            &options,
+        );
+
+        assert!(matches!(&result, Err(errors) if errors[0].contains(":slotted()")));
+    }
+
+    #[test]
+    fn test_keeps_global_styles_unscoped() {
+        let code = compile_source("<template><p></p></template><style>p { margin: 0; }</style>");
+
+        assert!(!code.contains("data-v-"));
+        assert!(code.contains(r#"const STYLE: &str = "p { margin: 0; }";"#));
+    }
+
+    #[test]
+    fn test_rejects_unsupported_style_language() {
+        let options = CompileOptions::default();
+        let result = compile(
+            r#"<template></template><style lang="scss">p { }</style>"#.to_string(),
+            &options,
+        );
+
+        assert_eq!(
+            result,
+            Err(vec![
+                "src/App.vue: Unsupported style language: scss".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_generates_style_module_accessor() {
+        let code = compile_source(
+            r#"<template><button :class="style.text_danger">Hi</button></template>
+            <style module>.text-danger { color: red; }</style>"#,
+        );
+        let scope_id = style::scope_id("src/App.vue");
+
+        assert!(code.contains(&format!(".text-danger_{scope_id} {{ color: red; }}")));
+        assert!(code.contains("text_danger: &'static str"));
+        assert!(code.contains(&format!("text_danger: \"text-danger_{scope_id}\"")));
+        assert!(code.contains("let style = CssModule0"));
+        assert!(
+            code.contains("bind_class_list(parents.last().unwrap(), &[], &(style.text_danger))")
+        );
+    }
+
+    #[test]
+    fn test_binds_class_and_style() {
+        let code = compile_source(
+            r#"<template><p class="card big" :class="[{ active: is_active, 'text-danger': has_error }, extra]"
+            :style="{ fontSize: size, '--gap': gap }" :title="title"></p></template>"#,
+        );
+
+        assert!(code.contains(r#"set_attribute("class", "card big")"#));
+        assert!(
+            code.contains(r#"bind_class_toggle(parents.last().unwrap(), "active", &(is_active))"#)
+        );
+        assert!(code.contains(
+            r#"bind_class_toggle(parents.last().unwrap(), "text-danger", &(has_error))"#
+        ));
+        assert!(code
+            .contains(r#"bind_class_list(parents.last().unwrap(), &["card", "big"], &(extra))"#));
+        assert!(
+            code.contains(r#"bind_style_property(parents.last().unwrap(), "font-size", &(size))"#)
+        );
+        assert!(code.contains(r#"bind_style_property(parents.last().unwrap(), "--gap", &(gap))"#));
+        assert!(code.contains(r#"bind_attribute(parents.last().unwrap(), "title", &(title))"#));
+        assert!(code.contains("mod villain_runtime"));
+    }
+
+    #[test]
+    fn test_binds_dynamic_names_and_properties() {
+        let code = compile_source(
+            r#"<template><input :[name]="v" :value.prop="text" .checked="done" :view-box.camel="b" :title.attr="t"/></template>"#,
+        );
+
+        assert!(code.contains(
+            "bind_attribute(parents.last().unwrap(), &ToString::to_string(&(name)), &(v))"
+        ));
+        assert!(code.contains(r#"bind_property(parents.last().unwrap(), "value", &(text))"#));
+        assert!(code.contains(r#"bind_property(parents.last().unwrap(), "checked", &(done))"#));
+        assert!(code.contains(r#"bind_attribute(parents.last().unwrap(), "viewBox", &(b))"#));
+        assert!(code.contains(r#"bind_attribute(parents.last().unwrap(), "title", &(t))"#));
+
+        for template in [
+            r#"<template><p v-bind="attributes"/></template>"#,
+            r#"<template><p :title/></template>"#,
+            r#"<template><p :title.sync="t"/></template>"#,
+            r#"<template><p :title.prop.attr="t"/></template>"#,
+            r#"<template><p :[name].camel="t"/></template>"#,
+        ] {
+            let errors = compile(template.to_string(), &CompileOptions::default()).unwrap_err();
+            assert_eq!(errors.len(), 1, "{template}");
         }
     }
 
-    for child in &root.children {
-        convert_children(&mut code, child);
+    #[test]
+    fn test_binds_show() {
+        let code = compile_source(r#"<template><p v-show="is_open">Hi</p></template>"#);
+
+        assert!(code.contains("bind_show(parents.last().unwrap(), &(is_open), None)"));
+        assert_eq!(
+            compile(
+                "<template><p v-show></p></template>".to_string(),
+                &CompileOptions::default()
+            ),
+            Err(vec![
+                "src/App.vue: v-show requires an expression".to_string()
+            ])
+        );
     }
 
-    format!(
-        "fn template(document: web_sys::Document, root: web_sys::Element) {{
-            // Stack of parents since nodes as nested and we basically emulate recursion
-            use futures_signals::signal::Mutable;
-            use futures_signals::signal::SignalExt;
-            use wasm_bindgen_futures::spawn_local;
+    #[test]
+    fn test_binds_html() {
+        let code = compile_source(r#"<template><div v-html="content"></div></template>"#);
 
-            let msg = Mutable::new(1);
-            let mut parents = vec![root];
-            {code}
-        }}"
-    )
-    .parse()
-    .unwrap()
+        assert!(code.contains("bind_html(parents.last().unwrap(), &(content))"));
+        assert!(code.contains("pub struct SanitizedHtml(String);"));
+        assert_eq!(
+            compile(
+                r#"<template><div v-html="content">Hi</div></template>"#.to_string(),
+                &CompileOptions::default()
+            ),
+            Err(vec![
+                "src/App.vue: v-html on <div> would overwrite its children, remove either"
+                    .to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_renders_text_and_interpolations() {
+        let code = compile_source(r#"<template><p>Hi "you" {{ name }}</p></template>"#);
+
+        assert!(code.contains(r#"create_text_node("Hi \"you\" ")"#));
+        assert!(code.contains("bind_text_node(document, parents.last().unwrap(), &(name))"));
+    }
+
+    #[test]
+    fn test_applies_template_delimiters_without_rendering_them() {
+        let code = compile_source(r#"<template delimiters="[[ ]]"><p>[[ name ]]</p></template>"#);
+
+        assert!(code.contains("bind_text_node(document, parents.last().unwrap(), &(name))"));
+        assert!(!code.contains("\"delimiters\""));
+    }
+
+    #[test]
+    fn test_binds_text_once_and_cloak() {
+        let code = compile_source(
+            r#"<template><div v-cloak><p v-text="a"></p><p v-once :title="b">{{ c }}</p><i>{{ d }}</i></div></template>"#,
+        );
+
+        assert!(code.contains("bind_text(parents.last().unwrap(), &(a))"));
+        assert!(code.contains(
+            "bind_attribute(parents.last().unwrap(), \"title\", &villain_runtime::Once(&(b)))"
+        ));
+        assert!(code.contains(
+            "bind_text_node(document, parents.last().unwrap(), &villain_runtime::Once(&(c)))"
+        ));
+        assert!(code.contains("bind_text_node(document, parents.last().unwrap(), &(d))"));
+        assert!(code.contains(r#"set_attribute("v-cloak", "")"#));
+        assert!(code.contains("villain_runtime::uncloak(parents.last().unwrap());"));
+    }
+
+    #[test]
+    fn test_renders_v_pre_content_as_written() {
+        let code = compile_source(r#"<template><p v-pre>{{ raw }}</p></template>"#);
+
+        assert!(code.contains(r#"create_text_node("{{ raw }}")"#));
+        assert!(!code.contains("villain_runtime::bind_text_node"));
+    }
+
+    #[test]
+    fn test_gates_bindings_in_v_memo() {
+        let code = compile_source(
+            r#"<template><li :title="t" v-memo="[id, selected]"><b v-memo="[]">{{ label }}</b></li><p>{{ other }}</p></template>"#,
+        );
+
+        assert!(code.contains("let memo_0 = villain_runtime::Memo::new();"));
+        assert!(code.contains("memo_0.depend(&(id));"));
+        assert!(code.contains("memo_0.depend(&(selected));"));
+        assert!(code.contains("let memo_1 = villain_runtime::Memo::new();"));
+        assert!(code.contains("\"title\", &villain_runtime::Memoized(&(t), &memo_0)"));
+        assert!(code.contains(
+            "&villain_runtime::Memoized(&villain_runtime::Memoized(&(label), &memo_0), &memo_1)"
+        ));
+        assert!(code.contains("bind_text_node(document, parents.last().unwrap(), &(other))"));
+
+        assert_eq!(
+            compile(
+                r#"<template><p v-memo="id"></p></template>"#.to_string(),
+                &CompileOptions::default()
+            ),
+            Err(vec![
+                "src/App.vue: v-memo requires an array of dependencies, e.g. v-memo=\"[a, b]\""
+                    .to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_binds_custom_directives() {
+        let options = CompileOptions {
+            directives: vec![
+                ("focus".to_string(), "Focus".to_string()),
+                (
+                    "click_outside".to_string(),
+                    "crate :: ClickOutside".to_string(),
+                ),
+            ],
+            ..CompileOptions::default()
+        };
+        let code = compile(
+            r#"<template><input v-focus/><div v-click-outside:[side].stop.prevent="close"></div></template>"#
+                .to_string(),
+            &options,
+        )
+        .unwrap();
+        code.parse::<proc_macro2::TokenStream>().unwrap();
+
+        assert!(
+            code.contains("bind_directive::<Focus, _>(parents.last().unwrap(), None, &[], &(()))")
+        );
+        assert!(code.contains(
+            r#"bind_directive::<crate :: ClickOutside, _>(parents.last().unwrap(), Some(ToString::to_string(&(side))), &["stop", "prevent"], &(close))"#
+        ));
+        assert!(code.contains("pub mod villain_runtime"));
+    }
+
+    #[test]
+    fn test_rejects_unknown_directives() {
+        let result = compile(
+            "<template><input v-focus/></template>".to_string(),
+            &CompileOptions::default(),
+        );
+
+        assert_eq!(
+            result,
+            Err(vec![
+                "src/App.vue: Unknown directive v-focus, register it with `directives = { focus: Type }`"
+                    .to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsupported_vue_directives() {
+        for (template, error) in [
+            (r#"<p v-if="shown"/>"#, "v-if is not supported yet"),
+            (r#"<p v-else/>"#, "v-else is not supported yet"),
+            (
+                r#"<li v-for="item in items"/>"#,
+                "v-for is not supported yet",
+            ),
+            (r#"<button @click="add"/>"#, "v-on is not supported yet"),
+            (
+                r#"<div><template #header/></div>"#,
+                "v-slot is not supported yet, except for the #fallback of <Suspense> and <ErrorBoundary>",
+            ),
+        ] {
+            let options = CompileOptions::default();
+            let result = compile(format!("<template>{template}</template>"), &options);
+
+            assert_eq!(
+                result,
+                Err(vec![format!("src/App.vue: {error}")]),
+                "{template}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_unknown_module_class() {
+        let options = CompileOptions::default();
+        let result = compile(
+            r#"<template><p :class="classes.missing"></p></template>
+            <style module="classes">.present { }</style>"#
+                .to_string(),
+            &options,
+        );
+
+        assert_eq!(
+            result,
+            Err(vec![
+                "src/App.vue: Class `missing` is not defined in <style module> `classes`"
+                    .to_string()
+            ])
+        );
+    }
+
+    /// Compiles `App.vue` of the fixture `name`, which `tests/runtime.rs` also
+    /// type-checks, and returns its path and the generated code
+    fn compile_fixture(name: &str) -> (String, String) {
+        let path = format!("tests/fixtures/{name}/App.vue");
+        let options = CompileOptions {
+            path: path.clone(),
+            ..CompileOptions::default()
+        };
+
+        let code = compile(std::fs::read_to_string(&path).unwrap(), &options).unwrap();
+        code.parse::<proc_macro2::TokenStream>().unwrap();
+
+        (path, code)
+    }
+
+    #[test]
+    fn test_maps_module_classes_to_valid_fields() {
+        let code = compile_source(
+            r#"<template><p :class="[style._2col, style.r#type, style.self_]"></p></template>
+            <style module>.2col { } .type { } .self { }</style>"#,
+        );
+
+        assert!(code.contains("_2col: &'static str"));
+        assert!(code.contains("r#type: &'static str"));
+        assert!(code.contains("self_: &'static str"));
+
+        let result = compile(
+            r#"<template><p/></template><style module>.a-b { } .a_b { }</style>"#.to_string(),
+            &CompileOptions::default(),
+        );
+        assert_eq!(
+            result,
+            Err(vec![
+                "src/App.vue: Classes `.a-b` and `.a_b` of <style module> `style` both map to the field `a_b`, rename one of them"
+                    .to_string()
+            ])
+        );
+    }
+
+    /// Writes `files` into a fresh directory and returns its path
+    fn write_files(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+        let directory = std::env::temp_dir().join(format!("villain-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        for (file, content) in files {
+            std::fs::write(directory.join(file), content).unwrap();
+        }
+
+        directory
+    }
+
+    #[test]
+    fn test_bundles_styles_of_reachable_components() {
+        let (path, code) = compile_fixture("bundle");
+
+        let item = resolve_component(&path, "Item");
+        let attribute = style::scope_attribute(&style::scope_id(&item));
+        let expected = format!(".app {{ }}.theme {{ }}.card {{ }}li[{attribute}] {{ }}");
+        assert!(code.contains(&format!("const STYLE: &str = {expected:?};")));
+
+        // Every component gets exactly one render function, even if used recursively
+        assert_eq!(
+            code.matches(&format!("fn {}(", render_function(&item)))
+                .count(),
+            1
+        );
+        assert_eq!(
+            code.matches("(document: &web_sys::Document, root: web_sys::Element)")
+                .count(),
+            3
+        );
+        assert!(!code.contains("render_unused"));
+    }
+
+    #[test]
+    fn test_renders_dynamic_components() {
+        let (path, code) = compile_fixture("dynamic");
+
+        let tab_a = render_function(&resolve_component(&path, "TabA"));
+        let tab_b = render_function(&resolve_component(&path, "TabB"));
+        assert!(code.contains(&format!(
+            "const TabA: villain_runtime::Component = villain_runtime::Component::new(\"TabA\", {tab_a});"
+        )));
+        assert!(!code.contains("const TabC"));
+        assert!(
+            code.contains("bind_component(document, parents.last().unwrap(), &(tab), None, None)")
+        );
+        assert!(code.contains(&format!(
+            "{tab_b}(document, parents.last().unwrap().clone())"
+        )));
+        assert_eq!(code.matches(&format!("fn {tab_a}(")).count(), 1);
+    }
+
+    #[test]
+    fn test_keeps_components_alive() {
+        let (_, code) = compile_fixture("keep_alive");
+
+        assert!(code.contains(
+            r#"bind_component(document, parents.last().unwrap(), &(tab), Some(villain_runtime::KeepAlive { include: &["TabA", "TabB"], exclude: &["TabC"], max: Some(2) }), None)"#
+        ));
+        assert!(code.contains("let _instance = villain_runtime::Instance::enter();"));
+
+        for template in [
+            "<template><KeepAlive><p>A</p></KeepAlive></template>",
+            r#"<template><KeepAlive max="all"><component :is="tab"/></KeepAlive></template>"#,
+        ] {
+            let errors = compile(template.to_string(), &CompileOptions::default()).unwrap_err();
+            assert_eq!(errors.len(), 1, "{template}");
+        }
+    }
+
+    #[test]
+    fn test_teleports_content() {
+        let code = compile_source(
+            r##"<template><div><Teleport to="#modals"><p>Modal</p></Teleport><Teleport :to="target" :disabled="inline"><b/></Teleport></div></template>"##,
+        );
+
+        assert!(code.contains(
+            r##"let teleport = villain_runtime::Teleport::new(document, parents.last().unwrap(), "#modals");"##
+        ));
+        assert!(code.contains("teleport.mount(&(false));"));
+        assert!(code.contains(
+            "let teleport = villain_runtime::Teleport::new(document, parents.last().unwrap(), &(target));"
+        ));
+        assert!(code.contains("teleport.mount(&(inline));"));
+
+        let options = CompileOptions::default();
+        let result = compile(
+            "<template><Teleport><p/></Teleport></template>".to_string(),
+            &options,
+        );
+        assert_eq!(result.unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn test_transitions_shown_elements_and_components() {
+        let code = compile_source(
+            r#"<template><Transition name="fade"><div v-show="open"><p v-show="inner"/></div></Transition><Transition><component :is="tab"/></Transition></template>"#,
+        );
+
+        assert!(code.contains(
+            r#"bind_show(parents.last().unwrap(), &(open), Some(villain_runtime::Transition::new("fade")))"#
+        ));
+        assert!(code.contains("bind_show(parents.last().unwrap(), &(inner), None)"));
+        assert!(code.contains(
+            r#"bind_component(document, parents.last().unwrap(), &(tab), None, Some(villain_runtime::Transition::new("v")))"#
+        ));
+
+        let options = CompileOptions::default();
+        for template in [
+            "<template><Transition><p/><p/></Transition></template>",
+            "<template><Transition><p/></Transition></template>",
+            "<template><Transition><Card v-show=\"open\"/></Transition></template>",
+            "<template><TransitionGroup><p/></TransitionGroup></template>",
+        ] {
+            let result = compile(template.to_string(), &options);
+            assert_eq!(result.unwrap_err().len(), 1, "{template}");
+        }
+    }
+
+    #[test]
+    fn test_suspends_async_components() {
+        let (path, code) = compile_fixture("suspense");
+
+        let suspense = code.find("villain_runtime::Suspense::new").unwrap();
+        let enter = code.find("let _boundary = boundary.enter();").unwrap();
+        let todos = render_function(&resolve_component(&path, "Todos"));
+        let content = code.find(&format!("{todos}(document")).unwrap();
+        let fallback = code.find("parents.push(boundary.fallback());").unwrap();
+        let loading = code.find("create_text_node(\"Loading\")").unwrap();
+        let mount = code.find("boundary.mount();").unwrap();
+        assert!(suspense < enter && enter < content && content < fallback);
+        assert!(fallback < loading && loading < mount);
+
+        let setup = code
+            .find("render_async(document, &root, async move {")
+            .unwrap();
+        let script = code.find("let todos = load().await;").unwrap();
+        let render = code
+            .find("move |document: &web_sys::Document, root: web_sys::Element| -> Result<")
+            .unwrap();
+        assert!(setup < script && script < render);
+        assert_eq!(code.matches("render_async(").count(), 1);
+    }
+
+    #[test]
+    fn test_renders_error_boundaries() {
+        let code = compile_source(
+            r#"<script lang="rust">let count = "1".parse::<u8>()?;</script>
+            <template><ErrorBoundary><p>{{ count }}</p><template #fallback="{ error }"><p>{{ error }}</p></template></ErrorBoundary></template>"#,
+        );
+
+        let setup = code
+            .find("villain_runtime::setup(move || -> Result<(), villain_runtime::Error> {")
+            .unwrap();
+        let script = code.find("let count = \"1\".parse::<u8>()?;").unwrap();
+        let boundary = code.find("villain_runtime::ErrorBoundary::new").unwrap();
+        let error = code.find("let error = boundary.error();").unwrap();
+        let fallback = code.find("parents.push(boundary.fallback());").unwrap();
+        let message = code
+            .find("bind_text_node(document, parents.last().unwrap(), &(error))")
+            .unwrap();
+        assert!(setup < script && script < boundary && boundary < error);
+        assert!(error < fallback && fallback < message);
+
+        let options = CompileOptions::default();
+        let template =
+            r#"<template><Suspense><p/><template #fallback="error"/></Suspense></template>"#;
+        let result = compile(template.to_string(), &options);
+        assert_eq!(result.unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn test_detects_top_level_awaits() {
+        assert!(is_async("let todos = load().await;"));
+        assert!(is_async("if ready { wait().await; }"));
+
+        for script in [
+            "spawn(async { load().await });",
+            "let load = async || fetch().await;",
+            "async fn load() { fetch().await }",
+            r#"let s = ".await"; // x.await"#,
+        ] {
+            assert!(!is_async(script), "{script}");
+        }
+    }
+
+    #[test]
+    fn test_model_listener_ends_with_component() {
+        let code = compile_source(r#"<template><input v-model="count"/></template>"#);
+
+        assert!(code.contains(
+            r#"villain_runtime::listen(
+        parents.last().unwrap(),
+        "keypress","#
+        ));
+        assert!(code.contains("cloned_count.set(input.value().parse::<i32>()?);"));
+        assert!(!code.contains("into_js_value"));
+    }
+
+    #[test]
+    fn test_declares_template_refs() {
+        let (_, code) = compile_fixture("refs");
+
+        let declared = code
+            .find("let input: villain_runtime::TemplateRef<web_sys::HtmlInputElement> = Default::default();")
+            .unwrap();
+        let script = code.find("villain_runtime::on_mounted(").unwrap();
+        let set = code
+            .find("template_ref_input.set(parents.last().unwrap());")
+            .unwrap();
+        assert!(declared < script && script < set);
+        assert!(
+            code.contains("let chart: villain_runtime::TemplateRef<web_sys::HtmlCanvasElement>")
+        );
+        assert!(code.contains("let area: villain_runtime::TemplateRef<web_sys::HtmlElement>"));
+        assert!(code.contains("let child: villain_runtime::ComponentRef = Default::default();"));
+        assert!(code.contains("template_ref_child.set_last_child();"));
+        assert!(!code.contains(r#"set_attribute("ref""#));
+
+        let errors = compile(
+            r#"<template><input ref="field"/><p ref="field"/><b ref="no-name"/></template>"#
+                .to_string(),
+            &CompileOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_finds_pascal_case_identifiers() {
+        assert_eq!(
+            pascal_case_identifiers(
+                r#"let a = if b { TabA } else { my_Tab }; let s = "TabB"; // TabC
+                const TabD: u8 = 1; let d = TabD; let v = vec![TabE, x::TabF]; Some(x_Y)"#
+            ),
+            vec!["TabA", "TabE", "Some"]
+        );
+    }
+
+    #[test]
+    fn test_reports_missing_components() {
+        let options = CompileOptions::default();
+        let result = compile("<template><Missing/></template>".to_string(), &options);
+
+        let diagnostics = result.unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0]
+            .starts_with("src/App.vue: Could not resolve component <Missing> at src/Missing.vue"));
+    }
+
+    #[test]
+    fn test_writes_bundle_below_out_dir() {
+        let directory = write_files("out-dir", &[]);
+
+        write_bundle(&directory, "css/app.css", ".a { }").unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(directory.join("css/app.css")).unwrap(),
+            ".a { }"
+        );
+    }
+
+    #[test]
+    fn test_reports_unterminated_interpolation_once() {
+        for template in [
+            "<template>{{ a",
+            "<template><div><p>{{ a",
+            "<template><p>{{ a</p></template>",
+        ] {
+            let errors = compile(template.to_string(), &CompileOptions::default()).unwrap_err();
+
+            assert_eq!(errors.len(), 1, "{template}: {errors:?}");
+            assert!(errors[0].contains("Unterminated interpolation"));
+        }
+    }
 }
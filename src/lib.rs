@@ -1,8 +1,15 @@
 extern crate proc_macro;
 use proc_macro::TokenStream;
+use syn::parse_macro_input;
 
+mod binding;
 mod expand;
+mod options;
 mod parser;
+mod runtime;
+mod style;
+
+use options::CompileOptions;
 
 #[allow(clippy::needless_doctest_main)]
 /// Due to limitations in rusts proc-macro API there is currently no
@@ -20,22 +27,25 @@ mod parser;
 ///    println!("cargo:rerun-if-changed=path/to/your/migration/dir/relative/to/your/Cargo.toml");
 /// }
 /// ```
-
 #[proc_macro]
 pub fn create_component(item: TokenStream) -> TokenStream {
-    expand::expand_template(item.to_string())
+    expand::expand_template(parse_macro_input!(item as CompileOptions))
 }
 
 /// Creates an entrypoint for the application using the specified `.vue` template file
-/// 
-/// ```
+///
+/// ```no_run
 /// use villain::create_entypoint;
-/// 
+///
+/// create_entypoint!("tests/fixtures/Hello.vue");
+///
 /// fn main() {
-///    println!("{:?}", create_entypoint!("src/App.vue"));
+///    let document = web_sys::window().unwrap().document().unwrap();
+///    let root = document.body().unwrap().into();
+///    template(document, root);
 /// }
 /// ```
 #[proc_macro]
 pub fn create_entypoint(item: TokenStream) -> TokenStream {
-    expand::expand_template(item.to_string())
+    expand::expand_template(parse_macro_input!(item as CompileOptions))
 }
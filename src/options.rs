@@ -0,0 +1,216 @@
+use std::path::{Component, Path};
+
+use crate::parser::whitespace::WhitespaceMode;
+use quote::ToTokens;
+use syn::{
+    braced, bracketed,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Ident, LitStr, Token,
+};
+
+/// Options passed to `create_component!` and `create_entypoint!`
+///
+/// ```ignore
+/// create_component!("src/Widget.vue", delimiters = ["[[", "]]"]);
+/// ```
+#[derive(Debug, PartialEq)]
+pub(crate) struct CompileOptions {
+    /// Path of the `.vue` file to compile
+    pub(crate) path: String,
+    /// Opening and closing interpolation delimiters, `{{` and `}}` by default
+    pub(crate) delimiters: Option<(String, String)>,
+    /// Whitespace handling of text nodes, `condense` by default
+    pub(crate) whitespace: WhitespaceMode,
+    /// File below `OUT_DIR` the styles of all components are written to instead
+    /// of being added to the document at runtime
+    pub(crate) css_bundle: Option<String>,
+    /// Custom directives by name, with the path of the type implementing them
+    pub(crate) directives: Vec<(String, String)>,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            path: "src/App.vue".to_string(),
+            delimiters: None,
+            whitespace: WhitespaceMode::default(),
+            css_bundle: None,
+            directives: Vec::new(),
+        }
+    }
+}
+
+impl Parse for CompileOptions {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut options = Self::default();
+        if input.is_empty() {
+            return Ok(options);
+        }
+
+        options.path = input.parse::<LitStr>()?.value();
+
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+
+            let name: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+
+            match name.to_string().as_str() {
+                "delimiters" => options.delimiters = Some(parse_delimiters(input)?),
+                "whitespace" => options.whitespace = parse_whitespace(input)?,
+                "css_bundle" => options.css_bundle = Some(parse_css_bundle(input)?),
+                "directives" => options.directives = parse_directives(input)?,
+                _ => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        format!("Unknown option `{name}`"),
+                    ))
+                }
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Parses a delimiters pair such as `["[[", "]]"]`
+fn parse_delimiters(input: ParseStream) -> syn::Result<(String, String)> {
+    let content;
+    let brackets = bracketed!(content in input);
+    let delimiters: Vec<LitStr> = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?
+        .into_iter()
+        .collect();
+
+    match delimiters.as_slice() {
+        [open, close] if !open.value().is_empty() && !close.value().is_empty() => {
+            Ok((open.value(), close.value()))
+        }
+        _ => Err(syn::Error::new(
+            brackets.span.join(),
+            "Expected two non-empty delimiters, e.g. `[\"[[\", \"]]\"]`",
+        )),
+    }
+}
+
+/// Parses a whitespace mode, either `"condense"` or `"preserve"`
+fn parse_whitespace(input: ParseStream) -> syn::Result<WhitespaceMode> {
+    let mode: LitStr = input.parse()?;
+
+    WhitespaceMode::from_name(&mode.value())
+        .ok_or_else(|| syn::Error::new(mode.span(), "Expected either \"condense\" or \"preserve\""))
+}
+
+/// Parses the file name of the CSS bundle, which has to stay inside `OUT_DIR`
+fn parse_css_bundle(input: ParseStream) -> syn::Result<String> {
+    let file: LitStr = input.parse()?;
+    let value = file.value();
+
+    let path = Path::new(&value);
+    if value.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(syn::Error::new(
+            file.span(),
+            "Expected a relative path below OUT_DIR, e.g. \"app.css\"",
+        ));
+    }
+
+    Ok(value)
+}
+
+/// Parses custom directives such as `{ focus: Focus, click_outside: crate::ClickOutside }`
+fn parse_directives(input: ParseStream) -> syn::Result<Vec<(String, String)>> {
+    let content;
+    braced!(content in input);
+
+    let entries =
+        Punctuated::<(Ident, syn::Path), Token![,]>::parse_terminated_with(&content, |entry| {
+            let name: Ident = entry.parse()?;
+            entry.parse::<Token![:]>()?;
+            Ok((name, entry.parse()?))
+        })?;
+
+    Ok(entries
+        .into_iter()
+        .map(|(name, path)| (name.to_string(), path.to_token_stream().to_string()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_path_only() {
+        let options: CompileOptions = syn::parse_str(r#""src/Widget.vue""#).unwrap();
+
+        assert_eq!(options.path, "src/Widget.vue");
+        assert_eq!(options.delimiters, None);
+    }
+
+    #[test]
+    fn test_parses_empty_input_as_default() {
+        let options: CompileOptions = syn::parse_str("").unwrap();
+
+        assert_eq!(options, CompileOptions::default());
+    }
+
+    #[test]
+    fn test_parses_delimiters() {
+        let options: CompileOptions =
+            syn::parse_str(r#""src/Widget.vue", delimiters = ["${", "}"]"#).unwrap();
+
+        assert_eq!(
+            options.delimiters,
+            Some(("${".to_string(), "}".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parses_whitespace() {
+        let options: CompileOptions =
+            syn::parse_str(r#""src/Widget.vue", whitespace = "preserve""#).unwrap();
+
+        assert_eq!(options.whitespace, WhitespaceMode::Preserve);
+        assert!(syn::parse_str::<CompileOptions>(r#""a.vue", whitespace = "trim""#).is_err());
+    }
+
+    #[test]
+    fn test_parses_css_bundle() {
+        let options: CompileOptions =
+            syn::parse_str(r#""src/App.vue", css_bundle = "css/app.css""#).unwrap();
+
+        assert_eq!(options.css_bundle, Some("css/app.css".to_string()));
+        assert!(syn::parse_str::<CompileOptions>(r#""a.vue", css_bundle = "../app.css""#).is_err());
+        assert!(syn::parse_str::<CompileOptions>(r#""a.vue", css_bundle = "/app.css""#).is_err());
+    }
+
+    #[test]
+    fn test_parses_directives() {
+        let options: CompileOptions = syn::parse_str(
+            r#""src/App.vue", directives = { focus: Focus, click_outside: crate::ClickOutside }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            options.directives,
+            vec![
+                ("focus".to_string(), "Focus".to_string()),
+                (
+                    "click_outside".to_string(),
+                    "crate :: ClickOutside".to_string()
+                ),
+            ]
+        );
+        assert!(syn::parse_str::<CompileOptions>(r#""a.vue", directives = { focus }"#).is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_delimiters() {
+        assert!(syn::parse_str::<CompileOptions>(r#""a.vue", delimiters = ["[["]"#).is_err());
+        assert!(syn::parse_str::<CompileOptions>(r#""a.vue", delimiters = ["", "]]"]"#).is_err());
+        assert!(syn::parse_str::<CompileOptions>(r#""a.vue", unknown = 1"#).is_err());
+    }
+}
@@ -1,4 +1,6 @@
 #![allow(clippy::module_inception)]
 pub(crate) mod parser;
 pub(crate) mod scanner;
+pub(crate) mod sfc;
 pub(crate) mod token;
+pub(crate) mod whitespace;
@@ -1,8 +1,10 @@
+use std::ops::Range;
+
 use thiserror::Error;
 
-use super::token::{Token, TokenType};
+use super::token::{Directive, Token, TokenType};
 
-#[derive(Error, Debug, PartialEq)]
+#[derive(Error, Debug, PartialEq, Clone)]
 pub(crate) enum ParserError {
     #[error("Unexpected end of file at position {0}")]
     UnexpectedEof(usize),
@@ -21,7 +23,10 @@ pub(crate) enum NodeType {
     Tag(String),
     Code(String),
     Text(String),
-    Attribute(String, Option<Token>, bool),
+    Attribute(String, Option<Token>),
+    Directive(Directive),
+    /// Placeholder for a part of the input that could not be parsed
+    Error(ParserError),
 }
 
 /// Represents a node in the HTML Syntax Tree
@@ -29,6 +34,8 @@ pub(crate) enum NodeType {
 pub(crate) struct Node {
     pub(crate) node_type: NodeType,
     pub(crate) children: Vec<Node>,
+    /// For tags, the positions from the opening `<` up to the closing tag
+    pub(crate) span: Range<usize>,
 }
 
 impl Node {
@@ -36,6 +43,7 @@ impl Node {
         Self {
             node_type,
             children: Vec::new(),
+            span: 0..0,
         }
     }
 
@@ -43,17 +51,78 @@ impl Node {
     pub(crate) fn add_child(&mut self, node: Node) {
         self.children.push(node);
     }
+
+    /// Attributes and directives are children of their tag but not part of its content
+    pub(crate) fn is_attribute(&self) -> bool {
+        matches!(
+            self.node_type,
+            NodeType::Attribute(..) | NodeType::Directive(_)
+        )
+    }
 }
 
 /// Represents the HTML Syntax Tree as a vector of syntax tokens
 pub(crate) struct Parser {
     tokens: Vec<Token>,
+    /// Names of the tags that are currently open, innermost last
+    open_tags: Vec<String>,
+    /// Position of the last token that was consumed
+    position: usize,
+    recovering: bool,
+    errors: Vec<ParserError>,
 }
 
 impl Parser {
     pub(crate) fn new(mut tokens: Vec<Token>) -> Self {
         tokens.reverse();
-        Self { tokens }
+        Self {
+            tokens,
+            open_tags: Vec::new(),
+            position: 0,
+            recovering: false,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Parses all tokens without stopping at the first error. Every error is
+    /// recorded and replaced by a `NodeType::Error` node, parsing resumes at the
+    /// next tag boundary.
+    pub(crate) fn parse_recovering(mut self) -> (Node, Vec<ParserError>) {
+        self.recovering = true;
+        // In recovering mode `parse` never fails, errors are collected instead
+        let root = self
+            .parse()
+            .unwrap_or_else(|e| Node::new(NodeType::Error(e)));
+
+        (root, self.errors)
+    }
+
+    /// Records the error and returns a node standing in for the broken input if
+    /// the parser is recovering, otherwise returns the error
+    fn recover(&mut self, error: ParserError) -> Result<Node, ParserError> {
+        if !self.recovering {
+            return Err(error);
+        }
+
+        // Every tag left open at the end of the file runs into the same error,
+        // while equal errors elsewhere are separate mistakes
+        let repeated =
+            matches!(error, ParserError::UnexpectedEof(_)) && self.errors.contains(&error);
+        if !repeated {
+            self.errors.push(error.clone());
+        }
+        Ok(Node::new(NodeType::Error(error)))
+    }
+
+    /// Drops tokens until the next tag boundary
+    fn skip_to_tag_boundary(&mut self) {
+        while let Some(token) = self.peek() {
+            if matches!(token.token_type, TokenType::TagOpen | TokenType::TagClose) {
+                break;
+            }
+
+            self.next();
+        }
     }
 
     fn peek(&self) -> Option<&Token> {
@@ -61,7 +130,12 @@ impl Parser {
     }
 
     fn next(&mut self) -> Option<Token> {
-        self.tokens.pop()
+        let token = self.tokens.pop();
+        if let Some(token) = &token {
+            self.position = token.position;
+        }
+
+        token
     }
 
     /// Expects the next token to be of the specified `TokenType`
@@ -73,7 +147,7 @@ impl Parser {
                 Err(ParserError::UnexpectedToken(token))
             }
         } else {
-            Err(ParserError::UnexpectedEof(0))
+            Err(ParserError::UnexpectedEof(self.position))
         }
     }
 
@@ -84,7 +158,7 @@ impl Parser {
                 return Ok(self.next());
             }
         } else {
-            return Err(ParserError::UnexpectedEof(0));
+            return Err(ParserError::UnexpectedEof(self.position));
         }
 
         Ok(None)
@@ -102,43 +176,95 @@ impl Parser {
         Ok(Node::new(NodeType::Code(code)))
     }
 
-    fn parse_attribute(&mut self, is_bound: bool) -> Result<Node, ParserError> {
-        if is_bound {
-            self.expect(TokenType::Colon)?;
-        }
+    fn parse_attribute(&mut self) -> Result<Node, ParserError> {
         let attribute = self.expect(TokenType::Attribute)?.value.unwrap();
         let value = self.take_if_present(TokenType::AttributeValue)?;
 
-        Ok(Node::new(NodeType::Attribute(attribute, value, is_bound)))
+        Ok(Node::new(NodeType::Attribute(attribute, value)))
+    }
+
+    fn parse_directive(&mut self) -> Result<Node, ParserError> {
+        let token = self.next().unwrap();
+        let TokenType::Directive(directive) = token.token_type else {
+            return Err(ParserError::UnexpectedToken(token));
+        };
+        let value = self
+            .take_if_present(TokenType::AttributeValue)?
+            .and_then(|value| value.value);
+
+        Ok(Node::new(NodeType::Directive(Directive {
+            value,
+            ..*directive
+        })))
     }
 
     fn parse_tag(&mut self) -> Result<Node, ParserError> {
         let open_tag = self.next().unwrap();
-        let tag_name = open_tag.value.as_ref().unwrap();
+        let tag_name = open_tag.value.unwrap();
         let mut node = Node::new(NodeType::Tag(tag_name.clone()));
 
-        while let Some(token) = self.peek() {
-            let attribute = match token.token_type {
-                TokenType::Colon => self.parse_attribute(true)?,
-                TokenType::Attribute => self.parse_attribute(false)?,
-                TokenType::TagOpen => self.parse_tag()?,
-                TokenType::TextNode => self.parse_text_node()?,
-                TokenType::Interpolation => self.parse_interpolation_node()?,
-                _ => break,
+        self.open_tags.push(tag_name);
+        let result = self.parse_tag_content(&mut node);
+        self.open_tags.pop();
+        node.span = open_tag.position..self.position;
+
+        result.map(|_| node)
+    }
+
+    /// Parses attributes and children of the innermost open tag up to and
+    /// including its closing tag
+    fn parse_tag_content(&mut self, node: &mut Node) -> Result<(), ParserError> {
+        let tag_name = self.open_tags.last().unwrap().clone();
+
+        loop {
+            let Some(token) = self.peek() else {
+                let error = self.recover(ParserError::UnexpectedEof(self.position))?;
+                node.add_child(error);
+
+                return Ok(());
             };
 
-            node.add_child(attribute);
-        }
+            let child = match token.token_type {
+                TokenType::Directive(_) => self.parse_directive(),
+                TokenType::Attribute => self.parse_attribute(),
+                TokenType::TagOpen => self.parse_tag(),
+                TokenType::TextNode => self.parse_text_node(),
+                TokenType::Interpolation => self.parse_interpolation_node(),
+                TokenType::TagClose => {
+                    let closing = self.peek().unwrap().value.clone().unwrap_or_default();
+                    if closing == tag_name {
+                        self.next();
+                        return Ok(());
+                    }
+
+                    let error = ParserError::UnmatchingClosing(tag_name.clone(), closing.clone());
+                    let ancestors = &self.open_tags[..self.open_tags.len() - 1];
+                    if ancestors.contains(&closing) {
+                        // The closing tag belongs to an ancestor, so this tag is
+                        // implicitly closed and the ancestor consumes the token
+                        node.add_child(self.recover(error)?);
+                        return Ok(());
+                    }
+
+                    // A stray closing tag without any matching open tag is dropped
+                    self.next();
+                    Err(error)
+                }
+                _ => {
+                    let token = self.next().unwrap();
+                    Err(ParserError::UnexpectedToken(token))
+                }
+            };
 
-        let closing = self.expect(TokenType::TagClose)?;
-        if closing.value.as_ref() != open_tag.value.as_ref() {
-            return Err(ParserError::UnmatchingClosing(
-                closing.value.as_ref().unwrap().to_string(),
-                open_tag.value.as_ref().unwrap().to_string(),
-            ));
+            match child {
+                Ok(child) => node.add_child(child),
+                Err(e) => {
+                    let error = self.recover(e)?;
+                    node.add_child(error);
+                    self.skip_to_tag_boundary();
+                }
+            }
         }
-
-        Ok(node)
     }
 
     fn parse(&mut self) -> Result<Node, ParserError> {
@@ -146,12 +272,22 @@ impl Parser {
 
         while let Some(token) = self.peek() {
             let next = match token.token_type {
-                TokenType::TagOpen => self.parse_tag()?,
-                TokenType::TextNode => self.parse_text_node()?,
-                _ => return Err(ParserError::UnexpectedToken(token.clone())),
+                TokenType::TagOpen => self.parse_tag(),
+                TokenType::TextNode => self.parse_text_node(),
+                _ => {
+                    let token = self.next().unwrap();
+                    Err(ParserError::UnexpectedToken(token))
+                }
             };
 
-            root.add_child(next);
+            match next {
+                Ok(next) => root.add_child(next),
+                Err(e) => {
+                    let error = self.recover(e)?;
+                    root.add_child(error);
+                    self.skip_to_tag_boundary();
+                }
+            }
         }
 
         Ok(root)
@@ -212,9 +348,37 @@ mod tests {
         let expected = Node::new(NodeType::Attribute(
             "class".to_string(),
             Some(Token::new_with_value(TokenType::AttributeValue, 0, "foo")),
-            false,
         ));
-        let actual = parser.parse_attribute(false).unwrap();
+        let actual = parser.parse_attribute().unwrap();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_parse_directive() {
+        let tokens = vec![
+            Token::new_with_value(
+                TokenType::Directive(Box::new(Directive {
+                    name: "on".to_string(),
+                    arg: Some("keyup".to_string()),
+                    modifiers: vec!["enter".to_string()],
+                    ..Default::default()
+                })),
+                0,
+                "@keyup.enter",
+            ),
+            Token::new_with_value(TokenType::AttributeValue, 0, "submit()"),
+        ];
+        let mut parser = Parser::new(tokens);
+
+        let expected = Node::new(NodeType::Directive(Directive {
+            name: "on".to_string(),
+            arg: Some("keyup".to_string()),
+            dynamic_arg: false,
+            modifiers: vec!["enter".to_string()],
+            value: Some("submit()".to_string()),
+        }));
+        let actual = parser.parse_directive().unwrap();
 
         assert_eq!(expected, actual);
     }
@@ -233,7 +397,6 @@ mod tests {
         expected.add_child(Node::new(NodeType::Attribute(
             "class".to_string(),
             Some(Token::new_with_value(TokenType::AttributeValue, 0, "foo")),
-            false,
         )));
         let actual = parser.parse_tag().unwrap();
 
@@ -256,7 +419,6 @@ mod tests {
         expected.add_child(Node::new(NodeType::Attribute(
             "class".to_string(),
             Some(Token::new_with_value(TokenType::AttributeValue, 0, "foo")),
-            false,
         )));
         expected.add_child(Node::new(NodeType::Tag("span".to_string())));
 
@@ -287,4 +449,82 @@ mod tests {
 
         let _s: String = parser.try_into().unwrap();
     }
+
+    #[test]
+    fn test_parse_recovering_collects_every_error() {
+        let tokens = vec![
+            Token::new_with_value(TokenType::TagOpen, 0, "div"),
+            Token::new_with_value(TokenType::AttributeValue, 5, "oops"),
+            Token::new_with_value(TokenType::TagOpen, 10, "span"),
+            Token::new_with_value(TokenType::TagClose, 16, "p"),
+            Token::new_with_value(TokenType::TagClose, 20, "div"),
+            Token::new_with_value(TokenType::TagOpen, 26, "p"),
+        ];
+        let parser = Parser::new(tokens);
+
+        let (root, errors) = parser.parse_recovering();
+
+        assert_eq!(
+            errors,
+            vec![
+                ParserError::UnexpectedToken(Token::new_with_value(
+                    TokenType::AttributeValue,
+                    5,
+                    "oops"
+                )),
+                ParserError::UnmatchingClosing("span".to_string(), "p".to_string()),
+                ParserError::UnmatchingClosing("span".to_string(), "div".to_string()),
+                ParserError::UnexpectedEof(26),
+            ]
+        );
+
+        let div = &root.children[0];
+        assert_eq!(div.node_type, NodeType::Tag("div".to_string()));
+        assert!(matches!(div.children[0].node_type, NodeType::Error(_)));
+        assert_eq!(div.children[1].node_type, NodeType::Tag("span".to_string()));
+        assert_eq!(root.children[1].node_type, NodeType::Tag("p".to_string()));
+    }
+
+    #[test]
+    fn test_parse_recovering_reports_equal_errors_in_different_places() {
+        let tokens = vec![
+            Token::new_with_value(TokenType::TagOpen, 0, "div"),
+            Token::new_with_value(TokenType::TagClose, 5, "span"),
+            Token::new_with_value(TokenType::TagClose, 12, "span"),
+            Token::new_with_value(TokenType::TagClose, 19, "div"),
+            Token::new_with_value(TokenType::TagOpen, 25, "p"),
+            Token::new_with_value(TokenType::TagOpen, 28, "b"),
+        ];
+        let parser = Parser::new(tokens);
+
+        let (_, errors) = parser.parse_recovering();
+
+        assert_eq!(
+            errors,
+            vec![
+                ParserError::UnmatchingClosing("div".to_string(), "span".to_string()),
+                ParserError::UnmatchingClosing("div".to_string(), "span".to_string()),
+                ParserError::UnexpectedEof(28),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_stops_at_first_error() {
+        let tokens = vec![
+            Token::new_with_value(TokenType::TagOpen, 0, "div"),
+            Token::new_with_value(TokenType::TagClose, 5, "span"),
+        ];
+        let parser = Parser::new(tokens);
+
+        let result: Result<Node, ParserError> = parser.try_into();
+
+        assert_eq!(
+            result,
+            Err(ParserError::UnmatchingClosing(
+                "div".to_string(),
+                "span".to_string()
+            ))
+        );
+    }
 }
@@ -1,4 +1,4 @@
-use super::token::{Token, TokenType};
+use super::token::{Directive, Token, TokenType};
 use thiserror::Error;
 
 #[derive(PartialEq)]
@@ -14,6 +14,15 @@ pub(crate) enum ScannerError {
 
     #[error("Unexpected end of file at position {0}")]
     UnexpectedEof(usize),
+
+    #[error("Unterminated interpolation starting at position {0}")]
+    UnterminatedInterpolation(usize),
+
+    #[error("Invalid directive {0} at position {1}")]
+    InvalidDirective(String, usize),
+
+    #[error("Invalid delimiters \"{0}\" at position {1}, expected an opening and a closing delimiter separated by whitespace")]
+    InvalidDelimiters(String, usize),
 }
 
 pub(crate) struct Scanner {
@@ -21,16 +30,71 @@ pub(crate) struct Scanner {
     position: usize,
     context: ScannerContext,
     tokens: Vec<Token>,
+    recovering: bool,
+    errors: Vec<ScannerError>,
+    /// Opening and closing interpolation delimiters
+    delimiters: (String, String),
+    /// Number of currently open tags
+    depth: usize,
+    /// Whether the input is a single file component, whose top level blocks
+    /// other than `<template>` are raw text
+    sfc: bool,
+    /// Depth of the tag with `v-pre` whose subtree is being scanned. Inside it
+    /// attributes are never directives and interpolations are plain text.
+    pre_depth: Option<usize>,
+    /// Index of the `TagOpen` token of the tag being scanned
+    tag_start: usize,
 }
 
 impl Scanner {
     pub fn new(input: String) -> Self {
+        Self::with_delimiters(input, ("{{".to_string(), "}}".to_string()))
+    }
+
+    /// Creates a scanner that uses custom interpolation delimiters such as `[[`
+    /// and `]]` instead of `{{` and `}}`
+    pub(crate) fn with_delimiters(input: String, delimiters: (String, String)) -> Self {
         Self {
             chars: input.chars().rev().collect(),
             position: 0,
             context: ScannerContext::BetweenTags,
             tokens: Vec::new(),
+            recovering: false,
+            errors: Vec::new(),
+            delimiters,
+            depth: 0,
+            sfc: false,
+            pre_depth: None,
+            tag_start: 0,
+        }
+    }
+
+    /// Treats the input as a single file component, so custom top level blocks
+    /// such as `<i18n>` or `<docs>` are scanned as raw text
+    pub(crate) fn sfc(mut self) -> Self {
+        self.sfc = true;
+        self
+    }
+
+    /// Scans the whole input without stopping at the first error. Every error is
+    /// recorded and the scanner resyncs at the next tag boundary, so the returned
+    /// tokens describe as much of the input as could be understood.
+    pub(crate) fn scan_recovering(mut self) -> (Vec<Token>, Vec<ScannerError>) {
+        self.recovering = true;
+        // In recovering mode `scan` never fails, errors are collected instead
+        let _ = self.scan();
+
+        (self.tokens, self.errors)
+    }
+
+    /// Records the error if the scanner is recovering, otherwise returns it
+    fn recover(&mut self, error: ScannerError) -> Result<(), ScannerError> {
+        if !self.recovering {
+            return Err(error);
         }
+
+        self.errors.push(error);
+        Ok(())
     }
 
     fn next(&mut self) -> Option<char> {
@@ -42,6 +106,28 @@ impl Scanner {
         self.chars.last().copied()
     }
 
+    /// Looks `offset` characters ahead without consuming anything
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars
+            .len()
+            .checked_sub(offset + 1)
+            .map(|index| self.chars[index])
+    }
+
+    fn starts_with(&self, pattern: &str) -> bool {
+        pattern
+            .chars()
+            .enumerate()
+            .all(|(offset, c)| self.peek_at(offset) == Some(c))
+    }
+
+    /// Consumes as many characters as `pattern` has
+    fn skip(&mut self, pattern: &str) {
+        for _ in pattern.chars() {
+            self.next();
+        }
+    }
+
     fn skip_whitespace(&mut self) {
         while let Some(c) = self.peek() {
             if c.is_whitespace() {
@@ -84,14 +170,61 @@ impl Scanner {
         value
     }
 
+    /// Collects an attribute name, which unlike a tag name may contain the
+    /// directive syntax characters such as `:`, `@`, `#`, `.` and `[]`
+    fn collect_attribute_name(&mut self) -> String {
+        let mut name = String::new();
+
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || matches!(c, '=' | '>' | '/' | '<' | '"' | '\'') {
+                break;
+            }
+
+            name.push(c);
+            self.next();
+        }
+
+        name
+    }
+
     fn scan_attribute(&mut self, position: usize) -> Result<(), ScannerError> {
         self.skip_whitespace();
-        let attribute_name = self.collect_name();
-        self.tokens.push(Token::new_with_value(
-            TokenType::Attribute,
-            position,
-            &attribute_name,
-        ));
+        let attribute_name = self.collect_attribute_name();
+        if attribute_name.is_empty() {
+            // Consume the character so scanning always makes progress
+            let c = self.next().unwrap_or_default();
+            return Err(ScannerError::UnexpectedCharacter(c, position));
+        }
+
+        let directive = if attribute_name == "v-pre" && self.pre_depth.is_none() {
+            // The attributes before `v-pre` on the same tag are plain as well
+            self.pre_depth = Some(self.depth);
+            for token in &mut self.tokens[self.tag_start..] {
+                if let TokenType::Directive(_) = token.token_type {
+                    token.token_type = TokenType::Attribute;
+                }
+            }
+
+            parse_directive(&attribute_name, position)
+        } else if self.pre_depth.is_some() {
+            Ok(None)
+        } else {
+            parse_directive(&attribute_name, position)
+        };
+        match &directive {
+            Ok(Some(directive)) => self.tokens.push(Token::new_with_value(
+                TokenType::Directive(Box::new(directive.clone())),
+                position,
+                &attribute_name,
+            )),
+            Ok(None) => self.tokens.push(Token::new_with_value(
+                TokenType::Attribute,
+                position,
+                &attribute_name,
+            )),
+            // The value is still consumed below so scanning can resume after it
+            Err(_) => {}
+        }
 
         if let Some('=') = self.peek() {
             self.next();
@@ -102,102 +235,231 @@ impl Scanner {
                 let value = &self.collect_until('"');
                 self.next();
 
-                self.tokens.push(Token::new_with_value(
-                    TokenType::AttributeValue,
-                    self.position,
-                    value,
-                ));
+                if directive.is_ok() {
+                    self.tokens.push(Token::new_with_value(
+                        TokenType::AttributeValue,
+                        self.position,
+                        value,
+                    ));
+                }
+            }
+        }
+
+        directive.map(|_| ())
+    }
+
+    /// Scans text up to the next tag, splitting it into text and interpolation
+    /// tokens. Text is kept exactly as written.
+    fn scan_text_node(&mut self) -> Result<(), ScannerError> {
+        let mut position = self.position;
+        let mut text = String::new();
+
+        while let Some(c) = self.peek() {
+            if c == '<' {
+                break;
+            }
+
+            if self.pre_depth.is_none() && self.starts_with(&self.delimiters.0) {
+                if !text.is_empty() {
+                    self.tokens
+                        .push(Token::new_with_value(TokenType::TextNode, position, &text));
+                    text.clear();
+                }
 
-                return Ok(());
+                self.scan_interpolation()?;
+                position = self.position;
+                continue;
             }
+
+            text.push(c);
+            self.next();
+        }
+
+        if !text.is_empty() {
+            self.tokens
+                .push(Token::new_with_value(TokenType::TextNode, position, &text));
         }
+
         Ok(())
     }
 
-    fn scan_text_node(&mut self) -> Result<(), ScannerError> {
+    /// Scans everything up to the closing tag of `tag` as a single text node
+    /// without looking for tags or interpolations
+    fn scan_raw_text(&mut self, tag: &str) {
         let position = self.position;
-        let value = self.collect_until('<');
-        self.scan_text_node_from_string(position, &value)?;
+        let closing = format!("</{tag}");
+        let mut text = String::new();
 
-        Ok(())
+        while let Some(c) = self.peek() {
+            if self.starts_with(&closing) {
+                break;
+            }
+
+            text.push(c);
+            self.next();
+        }
+
+        if !text.is_empty() {
+            self.tokens
+                .push(Token::new_with_value(TokenType::TextNode, position, &text));
+        }
     }
 
-    fn scan_text_node_from_string(
-        &mut self,
-        begin: usize,
-        text_to_search: &str,
-    ) -> Result<(), ScannerError> {
-        let mut position = begin;
-        let value = text_to_search;
-        // check if interpolation is inside value
-        if value.contains("{{") && value.contains("}}") {
-            // find how many times interpolation is inside value
-            let matches_open = value.matches("{{").count();
-            let matches_close = value.matches("}}").count();
-            // get index of first opening brace
-            let begin_index: usize;
-            let end_index: usize;
-            if matches_open == matches_close {
-                begin_index = value.find("{{").unwrap();
-                end_index = value.find("}}").unwrap();
-            } else if matches_close > matches_open && matches_open > 0 {
-                begin_index = value.rfind("{{").unwrap();
-                end_index = value.rfind("}}").unwrap();
-            } else if matches_open > matches_close && matches_close > 0 {
-                begin_index = value.rfind("{{").unwrap();
-                end_index = value.find("}}").unwrap();
-            } else {
-                return Err(ScannerError::UnexpectedCharacter('}', position));
+    /// Scans an interpolation starting at its opening delimiter. The interpolation
+    /// ends at the first closing delimiter that is neither inside a string or char
+    /// literal nor inside an open bracket of the expression. An unterminated one is
+    /// dropped up to the next `<`, so the tags after it are still scanned.
+    fn scan_interpolation(&mut self) -> Result<(), ScannerError> {
+        let position = self.position;
+        let (open, close) = self.delimiters.clone();
+        self.skip(&open);
+
+        let mut expression = String::new();
+        let mut depth = 0usize;
+
+        loop {
+            let Some(c) = self.peek() else {
+                // Everything after the opening delimiter was collected, put it back
+                self.position -= expression.chars().count();
+                self.chars.extend(expression.chars().rev());
+                while self.peek().is_some_and(|c| c != '<') {
+                    self.next();
+                }
+
+                return Err(ScannerError::UnterminatedInterpolation(position));
+            };
+
+            if depth == 0 && self.starts_with(&close) {
+                break;
             }
 
-            // push text before interpolation
-            let text_before = &value[..begin_index].trim_start();
-
-            if text_before.contains("{{") && text_before.contains("}}") {
-                self.scan_text_node_from_string(position, text_before)?;
-            } else if !text_before.is_empty() {
-                self.tokens.push(Token::new_with_value(
-                    TokenType::TextNode,
-                    position,
-                    text_before,
-                ));
-                position += text_before.len();
+            match c {
+                '"' => self.collect_string_literal(&mut expression),
+                'r' if self.raw_string_hashes().is_some() => {
+                    self.collect_raw_string_literal(&mut expression)
+                }
+                '\'' if self.peek_at(1) == Some('\\') || self.peek_at(2) == Some('\'') => {
+                    self.collect_char_literal(&mut expression)
+                }
+                '(' | '[' | '{' => {
+                    depth += 1;
+                    expression.push(c);
+                    self.next();
+                }
+                ')' | ']' | '}' => {
+                    depth = depth.saturating_sub(1);
+                    expression.push(c);
+                    self.next();
+                }
+                _ => {
+                    expression.push(c);
+                    self.next();
+                }
             }
+        }
+
+        let closing = self.position;
+        self.skip(&close);
+
+        let expression = expression.trim();
+        if expression.is_empty() {
+            let c = close.chars().next().unwrap_or_default();
+            return Err(ScannerError::UnexpectedCharacter(c, closing));
+        }
 
-            // push interpolation
-            let interpolation = &value[begin_index + 2..end_index].trim();
-
-            position += interpolation.len();
-            if interpolation.is_empty() {
-                return Err(ScannerError::UnexpectedCharacter('}', position));
-            } else if interpolation.contains("{{") && interpolation.contains("}}") {
-                self.scan_text_node_from_string(position, interpolation)?;
-            } else {
-                self.tokens.push(Token::new_with_value(
-                    TokenType::Interpolation,
-                    position,
-                    interpolation,
-                ));
+        self.tokens.push(Token::new_with_value(
+            TokenType::Interpolation,
+            position,
+            expression,
+        ));
+
+        Ok(())
+    }
+
+    /// Collects a `"` delimited string literal including its escape sequences
+    fn collect_string_literal(&mut self, value: &mut String) {
+        value.extend(self.next());
+
+        while let Some(c) = self.next() {
+            value.push(c);
+
+            match c {
+                '\\' => value.extend(self.next()),
+                '"' => break,
+                _ => {}
             }
+        }
+    }
+
+    /// Returns the number of `#` of the raw string literal starting at the
+    /// current position, if there is one
+    fn raw_string_hashes(&self) -> Option<usize> {
+        let mut hashes = 0;
+        while self.peek_at(hashes + 1) == Some('#') {
+            hashes += 1;
+        }
 
-            // push text after interpolation
-            let text_after = &value[end_index + 2..].trim_end();
-            if text_after.contains("{{") && text_after.contains("}}") {
-                self.scan_text_node_from_string(position, text_after)?;
-            } else if !text_after.is_empty() {
-                self.tokens.push(Token::new_with_value(
-                    TokenType::TextNode,
-                    position,
-                    &value[end_index + 2..],
-                ));
+        (self.peek_at(hashes + 1) == Some('"')).then_some(hashes)
+    }
+
+    /// Collects a raw string literal such as `r#"..."#`
+    fn collect_raw_string_literal(&mut self, value: &mut String) {
+        let hashes = self.raw_string_hashes().unwrap_or_default();
+        let terminator = format!("\"{}", "#".repeat(hashes));
+
+        // `r`, the hashes and the opening quote
+        for _ in 0..hashes + 2 {
+            value.extend(self.next());
+        }
+
+        while self.peek().is_some() {
+            if self.starts_with(&terminator) {
+                value.push_str(&terminator);
+                self.skip(&terminator);
+                break;
             }
 
-            Ok(())
-        } else {
-            // if there is no closing brace, add text to tokens
-            self.tokens
-                .push(Token::new_with_value(TokenType::TextNode, position, value));
-            Ok(())
+            value.extend(self.next());
+        }
+    }
+
+    /// Collects a char literal such as `'}'` or `'\n'`
+    fn collect_char_literal(&mut self, value: &mut String) {
+        value.extend(self.next());
+
+        while let Some(c) = self.next() {
+            value.push(c);
+
+            match c {
+                '\\' => value.extend(self.next()),
+                '\'' => break,
+                _ => {}
+            }
+        }
+    }
+
+    /// Switches to the delimiters given by a `delimiters="[[ ]]"` attribute if that
+    /// is the attribute that was just scanned
+    fn apply_delimiters_attribute(&mut self) -> Result<(), ScannerError> {
+        let [attribute, value] = match self.tokens.as_slice() {
+            [.., attribute, value] => [attribute, value],
+            _ => return Ok(()),
+        };
+
+        if attribute.token_type != TokenType::Attribute
+            || attribute.value.as_deref() != Some("delimiters")
+            || value.token_type != TokenType::AttributeValue
+        {
+            return Ok(());
+        }
+
+        let value = value.value.clone().unwrap_or_default();
+        match value.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [open, close] => {
+                self.delimiters = (open.to_string(), close.to_string());
+                Ok(())
+            }
+            _ => Err(ScannerError::InvalidDelimiters(value, attribute.position)),
         }
     }
 
@@ -210,7 +472,11 @@ impl Scanner {
             match c {
                 '<' => {
                     if self.context == ScannerContext::InTag {
-                        return Err(ScannerError::UnexpectedCharacter(c, position));
+                        // Resync by treating the unterminated tag as ended right here
+                        self.recover(ScannerError::UnexpectedCharacter(c, position))?;
+                        self.context = ScannerContext::BetweenTags;
+                        append_closing = false;
+                        continue;
                     }
                     self.next();
 
@@ -225,6 +491,22 @@ impl Scanner {
                             position,
                             &tag,
                         ));
+                        self.depth = self.depth.saturating_sub(1);
+                        if self.pre_depth.is_some_and(|depth| self.depth <= depth) {
+                            self.pre_depth = None;
+                        }
+
+                        self.skip_whitespace();
+                        match self.peek() {
+                            Some('>') => {}
+                            Some(c) => {
+                                // Resync at the end of the malformed closing tag
+                                self.recover(ScannerError::UnexpectedCharacter(c, self.position))?;
+                                self.collect_until('>');
+                            }
+                            None => self.recover(ScannerError::UnexpectedEof(self.position))?,
+                        }
+                        self.next();
 
                         continue;
                     }
@@ -234,6 +516,7 @@ impl Scanner {
 
                     tag = self.collect_name();
 
+                    self.tag_start = self.tokens.len();
                     self.tokens
                         .push(Token::new_with_value(TokenType::TagOpen, position, &tag));
 
@@ -242,6 +525,11 @@ impl Scanner {
                     // Continue attribute collection. We are now in a tag
                     self.context = ScannerContext::InTag;
                 }
+                _ if self.context == ScannerContext::BetweenTags => {
+                    if let Err(e) = self.scan_text_node() {
+                        self.recover(e)?;
+                    }
+                }
                 '/' => {
                     self.next();
 
@@ -264,39 +552,123 @@ impl Scanner {
                             ));
                             append_closing = false;
                             tag.clear();
+                            if self.pre_depth == Some(self.depth) {
+                                self.pre_depth = None;
+                            }
+                        } else {
+                            // Like in HTML, scripts and styles are raw text. So are the
+                            // custom top level blocks of a single file component.
+                            let is_custom_block = self.sfc && self.depth == 0 && tag != "template";
+                            if tag == "script" || tag == "style" || is_custom_block {
+                                self.scan_raw_text(&tag);
+                            }
+                            self.depth += 1;
                         }
 
                         self.context = ScannerContext::BetweenTags;
                     }
                 }
-                'a'..='z' | 'A'..='Z' | '0'..='9' | ':' | '{' => {
-                    if self.context == ScannerContext::InTag {
-                        if c == ':' {
-                            self.next();
-                            self.tokens.push(Token::new(TokenType::Colon, position));
-                            continue;
-                        }
-
-                        self.scan_attribute(position)?;
-                    } else {
-                        //
-                        self.scan_text_node()?;
-                    }
+                _ if c.is_whitespace() => {
+                    self.next();
                 }
                 _ => {
-                    self.next();
+                    if let Err(e) = self.scan_attribute(position) {
+                        self.recover(e)?;
+                    }
+
+                    // Only the outermost `<template>` configures the delimiters, nested
+                    // ones are fragments of the template
+                    if tag == "template" && self.depth == 0 {
+                        if let Err(e) = self.apply_delimiters_attribute() {
+                            self.recover(e)?;
+                        }
+                    }
                 }
             }
         }
 
         if self.context != ScannerContext::BetweenTags {
-            return Err(ScannerError::UnexpectedEof(self.position));
+            self.recover(ScannerError::UnexpectedEof(self.position))?;
         }
 
         Ok(&self.tokens)
     }
 }
 
+/// Parses an attribute name such as `v-on:click.prevent`, `:[key]`, `.prop`
+/// or `#header` into a directive. Plain attributes yield `None`.
+fn parse_directive(attribute: &str, position: usize) -> Result<Option<Directive>, ScannerError> {
+    let invalid = || ScannerError::InvalidDirective(attribute.to_string(), position);
+
+    let (name, rest) = if let Some(rest) = attribute.strip_prefix("v-") {
+        let end = rest.find([':', '.']).unwrap_or(rest.len());
+        match rest[end..].strip_prefix(':') {
+            Some(argument) => (&rest[..end], argument),
+            // Without an argument only modifiers may follow the name
+            None => (&rest[..end], &rest[end..]),
+        }
+    } else {
+        let shorthand = match attribute.chars().next() {
+            Some(':') | Some('.') => "bind",
+            Some('@') => "on",
+            Some('#') => "slot",
+            _ => return Ok(None),
+        };
+        (shorthand, &attribute[1..])
+    };
+
+    if name.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut directive = Directive {
+        name: name.to_string(),
+        ..Default::default()
+    };
+
+    let has_argument = !rest.is_empty() && !rest.starts_with('.');
+    let modifiers = if !has_argument {
+        rest
+    } else if let Some(dynamic) = rest.strip_prefix('[') {
+        let end = dynamic.find(']').ok_or_else(invalid)?;
+        if dynamic[..end].trim().is_empty() {
+            return Err(invalid());
+        }
+        directive.arg = Some(dynamic[..end].to_string());
+        directive.dynamic_arg = true;
+        &dynamic[end + 1..]
+    } else if name == "slot" {
+        // Slot names may contain dots, e.g. `#item.name`, so slots have no modifiers
+        directive.arg = Some(rest.to_string());
+        ""
+    } else {
+        let end = rest.find('.').unwrap_or(rest.len());
+        directive.arg = Some(rest[..end].to_string());
+        &rest[end..]
+    };
+
+    if !attribute.starts_with("v-") && directive.arg.is_none() {
+        // A shorthand without argument such as a lone `@` or `:`
+        return Err(invalid());
+    }
+
+    if !modifiers.is_empty() {
+        let modifiers = modifiers.strip_prefix('.').ok_or_else(invalid)?;
+        for modifier in modifiers.split('.') {
+            if modifier.is_empty() {
+                return Err(invalid());
+            }
+            directive.modifiers.push(modifier.to_string());
+        }
+    }
+
+    if attribute.starts_with('.') {
+        directive.modifiers.push("prop".to_string());
+    }
+
+    Ok(Some(directive))
+}
+
 impl TryInto<Vec<Token>> for Scanner {
     type Error = ScannerError;
 
@@ -359,7 +731,10 @@ mod tests {
         assert_eq!(tokens.len(), 4);
         assert_eq!(tokens[0].token_type, TokenType::TagOpen);
         assert_eq!(tokens[0].value.as_ref().unwrap(), "template");
-        assert_eq!(tokens[1].token_type, TokenType::Attribute);
+        assert_eq!(
+            tokens[1].token_type,
+            TokenType::Directive(Box::new(directive("model", None, false, &[])))
+        );
         assert_eq!(tokens[1].value.as_ref().unwrap(), "v-model");
         assert_eq!(tokens[2].token_type, TokenType::AttributeValue);
         assert_eq!(tokens[2].value.as_ref().unwrap(), "test");
@@ -450,18 +825,24 @@ mod tests {
         let scanner = Scanner::new(input);
 
         let tokens: Vec<Token> = scanner.try_into().unwrap();
-        assert_eq!(tokens.len(), 6);
+        assert_eq!(tokens.len(), 5);
         assert_eq!(tokens[0].token_type, TokenType::TagOpen);
         assert_eq!(tokens[0].value.as_ref().unwrap(), "template");
         assert_eq!(tokens[1].token_type, TokenType::Attribute);
         assert_eq!(tokens[1].value.as_ref().unwrap(), "attr");
-        assert_eq!(tokens[2].token_type, TokenType::Colon);
-        assert_eq!(tokens[3].token_type, TokenType::Attribute);
-        assert_eq!(tokens[3].value.as_ref().unwrap(), "attr2");
-        assert_eq!(tokens[4].token_type, TokenType::AttributeValue);
-        assert_eq!(tokens[4].value.as_ref().unwrap(), "100");
-        assert_eq!(tokens[5].token_type, TokenType::TagClose);
-        assert_eq!(tokens[5].value.as_ref().unwrap(), "template");
+        assert_eq!(
+            tokens[2].token_type,
+            TokenType::Directive(Box::new(Directive {
+                name: "bind".to_string(),
+                arg: Some("attr2".to_string()),
+                ..Default::default()
+            }))
+        );
+        assert_eq!(tokens[2].value.as_ref().unwrap(), ":attr2");
+        assert_eq!(tokens[3].token_type, TokenType::AttributeValue);
+        assert_eq!(tokens[3].value.as_ref().unwrap(), "100");
+        assert_eq!(tokens[4].token_type, TokenType::TagClose);
+        assert_eq!(tokens[4].value.as_ref().unwrap(), "template");
     }
 
     #[test]
@@ -470,15 +851,17 @@ mod tests {
         let scanner = Scanner::new(input);
 
         let tokens: Vec<Token> = scanner.try_into().unwrap();
-        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens.len(), 5);
         assert_eq!(tokens[0].token_type, TokenType::TagOpen);
         assert_eq!(tokens[0].value.as_ref().unwrap(), "div");
         assert_eq!(tokens[1].token_type, TokenType::TextNode);
         assert_eq!(tokens[1].value.as_ref().unwrap(), "Hello ");
         assert_eq!(tokens[2].token_type, TokenType::Interpolation);
         assert_eq!(tokens[2].value.as_ref().unwrap(), "username");
-        assert_eq!(tokens[3].token_type, TokenType::TagClose);
-        assert_eq!(tokens[3].value.as_ref().unwrap(), "div");
+        assert_eq!(tokens[3].token_type, TokenType::TextNode);
+        assert_eq!(tokens[3].value.as_ref().unwrap(), " ");
+        assert_eq!(tokens[4].token_type, TokenType::TagClose);
+        assert_eq!(tokens[4].value.as_ref().unwrap(), "div");
     }
 
     #[test]
@@ -571,24 +954,411 @@ mod tests {
         let scanner = Scanner::new(input);
 
         let tokens: Vec<Token> = scanner.try_into().unwrap();
-        assert_eq!(tokens.len(), 9);
+        assert_eq!(tokens.len(), 11);
         assert_eq!(tokens[0].token_type, TokenType::TagOpen);
         assert_eq!(tokens[0].value.as_ref().unwrap(), "template");
         assert_eq!(tokens[1].token_type, TokenType::TagOpen);
         assert_eq!(tokens[1].value.as_ref().unwrap(), "h1");
+        assert_eq!(tokens[2].token_type, TokenType::TextNode);
+        assert_eq!(tokens[2].value.as_ref().unwrap(), " ");
+        assert_eq!(tokens[3].token_type, TokenType::Interpolation);
+        assert_eq!(tokens[3].value.as_ref().unwrap(), "msg");
+        assert_eq!(tokens[4].token_type, TokenType::TextNode);
+        assert_eq!(tokens[4].value.as_ref().unwrap(), " ");
+        assert_eq!(tokens[5].token_type, TokenType::TagClose);
+        assert_eq!(tokens[5].value.as_ref().unwrap(), "h1");
+        assert_eq!(tokens[6].token_type, TokenType::TagOpen);
+        assert_eq!(tokens[6].value.as_ref().unwrap(), "input");
+        assert_eq!(
+            tokens[7].token_type,
+            TokenType::Directive(Box::new(directive("model", None, false, &[])))
+        );
+        assert_eq!(tokens[7].value.as_ref().unwrap(), "v-model");
+        assert_eq!(tokens[8].token_type, TokenType::AttributeValue);
+        assert_eq!(tokens[8].value.as_ref().unwrap(), "msg");
+        assert_eq!(tokens[9].token_type, TokenType::TagClose);
+        assert_eq!(tokens[9].value.as_ref().unwrap(), "input");
+        assert_eq!(tokens[10].token_type, TokenType::TagClose);
+        assert_eq!(tokens[10].value.as_ref().unwrap(), "template");
+    }
+
+    #[test]
+    fn test_recovering_collects_every_error() {
+        let input = "<div<span>{{ }}</span><p".to_string();
+        let scanner = Scanner::new(input);
+
+        let (tokens, errors) = scanner.scan_recovering();
+
+        assert_eq!(
+            errors,
+            vec![
+                ScannerError::UnexpectedCharacter('<', 4),
+                ScannerError::UnexpectedCharacter('}', 13),
+                ScannerError::UnexpectedEof(24),
+            ]
+        );
+        assert_eq!(tokens[0].value.as_ref().unwrap(), "div");
+        assert_eq!(tokens[1].token_type, TokenType::TagOpen);
+        assert_eq!(tokens[1].value.as_ref().unwrap(), "span");
+        assert_eq!(tokens[2].token_type, TokenType::TagClose);
+        assert_eq!(tokens[2].value.as_ref().unwrap(), "span");
+        assert_eq!(tokens[3].token_type, TokenType::TagOpen);
+        assert_eq!(tokens[3].value.as_ref().unwrap(), "p");
+    }
+
+    #[test]
+    fn test_scans_interpolation_with_closing_braces_in_string() {
+        let input = r#"<p>{{ format!("}}") }}</p>"#.to_string();
+        let scanner = Scanner::new(input);
+
+        let tokens: Vec<Token> = scanner.try_into().unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[1].token_type, TokenType::Interpolation);
+        assert_eq!(tokens[1].value.as_ref().unwrap(), r#"format!("}}")"#);
+        assert_eq!(tokens[1].position, 3);
+    }
+
+    #[test]
+    fn test_scans_interpolation_with_braces_in_literals() {
+        let input = r##"<p>{{ ['}', '\''].len() }}{{ r#"}}"#.len() }}</p>"##.to_string();
+        let scanner = Scanner::new(input);
+
+        let tokens: Vec<Token> = scanner.try_into().unwrap();
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[1].value.as_ref().unwrap(), r"['}', '\''].len()");
+        assert_eq!(tokens[2].value.as_ref().unwrap(), r##"r#"}}"#.len()"##);
+    }
+
+    #[test]
+    fn test_scans_multiple_interpolations_with_exact_text() {
+        let input = "<p>{{ a }} b {{ c }} d {{ e }}</p>".to_string();
+        let scanner = Scanner::new(input);
+
+        let tokens: Vec<Token> = scanner.try_into().unwrap();
+        let scanned: Vec<(TokenType, usize, &str)> = tokens
+            .iter()
+            .map(|t| {
+                (
+                    t.token_type.clone(),
+                    t.position,
+                    t.value.as_deref().unwrap(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            scanned,
+            vec![
+                (TokenType::TagOpen, 0, "p"),
+                (TokenType::Interpolation, 3, "a"),
+                (TokenType::TextNode, 10, " b "),
+                (TokenType::Interpolation, 13, "c"),
+                (TokenType::TextNode, 20, " d "),
+                (TokenType::Interpolation, 23, "e"),
+                (TokenType::TagClose, 30, "p"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scans_interpolation_containing_angle_brackets() {
+        let input = "<p>{{ a < b }} > c</p>".to_string();
+        let scanner = Scanner::new(input);
+
+        let tokens: Vec<Token> = scanner.try_into().unwrap();
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[1].value.as_ref().unwrap(), "a < b");
+        assert_eq!(tokens[2].token_type, TokenType::TextNode);
+        assert_eq!(tokens[2].value.as_ref().unwrap(), " > c");
+    }
+
+    #[test]
+    fn test_gracefully_handles_unterminated_interpolation() {
+        let input = "<p>{{ a </p>".to_string();
+        let scanner = Scanner::new(input);
+
+        let scan: Result<Vec<Token>, ScannerError> = scanner.try_into();
+
+        assert_eq!(
+            scan.unwrap_err(),
+            ScannerError::UnterminatedInterpolation(3)
+        );
+    }
+
+    #[test]
+    fn test_resyncs_after_unterminated_interpolation() {
+        let input = "<p>{{ a </p><b>{{ \"}}\" </b>".to_string();
+        let scanner = Scanner::new(input);
+
+        let (tokens, errors) = scanner.scan_recovering();
+
+        assert_eq!(
+            errors,
+            vec![
+                ScannerError::UnterminatedInterpolation(3),
+                ScannerError::UnterminatedInterpolation(15),
+            ]
+        );
+        let types: Vec<_> = tokens.iter().map(|token| &token.token_type).collect();
+        assert_eq!(
+            types,
+            [
+                &TokenType::TagOpen,
+                &TokenType::TagClose,
+                &TokenType::TagOpen,
+                &TokenType::TagClose
+            ]
+        );
+        assert_eq!(tokens[3].position, 23);
+    }
+
+    #[test]
+    fn test_scans_custom_delimiters() {
+        let input = "<p>{{ a }} [[ b ]]</p>".to_string();
+        let scanner = Scanner::with_delimiters(input, ("[[".to_string(), "]]".to_string()));
+
+        let tokens: Vec<Token> = scanner.try_into().unwrap();
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[1].token_type, TokenType::TextNode);
+        assert_eq!(tokens[1].value.as_ref().unwrap(), "{{ a }} ");
         assert_eq!(tokens[2].token_type, TokenType::Interpolation);
-        assert_eq!(tokens[2].value.as_ref().unwrap(), "msg");
-        assert_eq!(tokens[3].token_type, TokenType::TagClose);
-        assert_eq!(tokens[3].value.as_ref().unwrap(), "h1");
-        assert_eq!(tokens[4].token_type, TokenType::TagOpen);
-        assert_eq!(tokens[4].value.as_ref().unwrap(), "input");
-        assert_eq!(tokens[5].token_type, TokenType::Attribute);
-        assert_eq!(tokens[5].value.as_ref().unwrap(), "v-model");
-        assert_eq!(tokens[6].token_type, TokenType::AttributeValue);
-        assert_eq!(tokens[6].value.as_ref().unwrap(), "msg");
-        assert_eq!(tokens[7].token_type, TokenType::TagClose);
-        assert_eq!(tokens[7].value.as_ref().unwrap(), "input");
-        assert_eq!(tokens[8].token_type, TokenType::TagClose);
-        assert_eq!(tokens[8].value.as_ref().unwrap(), "template");
+        assert_eq!(tokens[2].value.as_ref().unwrap(), "b");
+        assert_eq!(tokens[2].position, 11);
+    }
+
+    #[test]
+    fn test_scans_delimiters_sharing_brace_with_expression() {
+        let input = "<p>${ {a: 1} }!</p>".to_string();
+        let scanner = Scanner::with_delimiters(input, ("${".to_string(), "}".to_string()));
+
+        let tokens: Vec<Token> = scanner.try_into().unwrap();
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[1].value.as_ref().unwrap(), "{a: 1}");
+        assert_eq!(tokens[2].value.as_ref().unwrap(), "!");
+    }
+
+    #[test]
+    fn test_scans_delimiters_attribute_on_template() {
+        let input = r#"<template delimiters="[[ ]]">{{ a }}[[ b ]]</template>"#.to_string();
+        let scanner = Scanner::new(input);
+
+        let tokens: Vec<Token> = scanner.try_into().unwrap();
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(tokens[3].token_type, TokenType::TextNode);
+        assert_eq!(tokens[3].value.as_ref().unwrap(), "{{ a }}");
+        assert_eq!(tokens[4].token_type, TokenType::Interpolation);
+        assert_eq!(tokens[4].value.as_ref().unwrap(), "b");
+    }
+
+    #[test]
+    fn test_ignores_delimiters_attribute_on_nested_template() {
+        let input =
+            r#"<template><template delimiters="[[ ]]">[[ a ]]</template>{{ b }}</template>"#;
+        let scanner = Scanner::new(input.to_string());
+
+        let tokens: Vec<Token> = scanner.try_into().unwrap();
+        assert_eq!(tokens[4].token_type, TokenType::TextNode);
+        assert_eq!(tokens[4].value.as_ref().unwrap(), "[[ a ]]");
+        assert_eq!(tokens[6].token_type, TokenType::Interpolation);
+        assert_eq!(tokens[6].value.as_ref().unwrap(), "b");
+    }
+
+    #[test]
+    fn test_gracefully_handles_invalid_delimiters_attribute() {
+        let input = r#"<template delimiters="[["></template>"#.to_string();
+        let scanner = Scanner::new(input);
+
+        let scan: Result<Vec<Token>, ScannerError> = scanner.try_into();
+
+        assert_eq!(
+            scan.unwrap_err(),
+            ScannerError::InvalidDelimiters("[[".to_string(), 10)
+        );
+    }
+
+    fn directive(
+        name: &str,
+        arg: Option<&str>,
+        dynamic_arg: bool,
+        modifiers: &[&str],
+    ) -> Directive {
+        Directive {
+            name: name.to_string(),
+            arg: arg.map(ToString::to_string),
+            dynamic_arg,
+            modifiers: modifiers.iter().map(ToString::to_string).collect(),
+            value: None,
+        }
+    }
+
+    #[test]
+    fn test_parses_directive_syntax() {
+        let cases = [
+            ("v-if", directive("if", None, false, &[])),
+            (
+                "v-on:keyup.enter",
+                directive("on", Some("keyup"), false, &["enter"]),
+            ),
+            (
+                "@click.stop.prevent",
+                directive("on", Some("click"), false, &["stop", "prevent"]),
+            ),
+            ("v-bind:[name]", directive("bind", Some("name"), true, &[])),
+            (
+                ":[key].camel",
+                directive("bind", Some("key"), true, &["camel"]),
+            ),
+            (
+                ".text-content",
+                directive("bind", Some("text-content"), false, &["prop"]),
+            ),
+            ("#header", directive("slot", Some("header"), false, &[])),
+            (
+                "#item.name",
+                directive("slot", Some("item.name"), false, &[]),
+            ),
+            ("v-slot", directive("slot", None, false, &[])),
+            ("v-model.trim", directive("model", None, false, &["trim"])),
+            (
+                "v-model:title.lazy",
+                directive("model", Some("title"), false, &["lazy"]),
+            ),
+        ];
+
+        for (attribute, expected) in cases {
+            assert_eq!(
+                parse_directive(attribute, 0),
+                Ok(Some(expected)),
+                "{attribute}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parses_plain_attribute_as_no_directive() {
+        assert_eq!(parse_directive("class", 0), Ok(None));
+        assert_eq!(parse_directive("data-v-model", 0), Ok(None));
+    }
+
+    #[test]
+    fn test_rejects_invalid_directives() {
+        for attribute in ["@", ":", "v-", "v-bind:[name", "@click..stop", "v-on:[]"] {
+            assert_eq!(
+                parse_directive(attribute, 3),
+                Err(ScannerError::InvalidDirective(attribute.to_string(), 3)),
+                "{attribute}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_scans_directives_with_values() {
+        let input = r#"<button @click="count += 1" #header v-on:keyup.enter="submit()"></button>"#
+            .to_string();
+        let scanner = Scanner::new(input);
+
+        let tokens: Vec<Token> = scanner.try_into().unwrap();
+        assert_eq!(tokens.len(), 7);
+        assert_eq!(
+            tokens[1].token_type,
+            TokenType::Directive(Box::new(directive("on", Some("click"), false, &[])))
+        );
+        assert_eq!(tokens[2].value.as_ref().unwrap(), "count += 1");
+        assert_eq!(
+            tokens[3].token_type,
+            TokenType::Directive(Box::new(directive("slot", Some("header"), false, &[])))
+        );
+        assert_eq!(
+            tokens[4].token_type,
+            TokenType::Directive(Box::new(directive("on", Some("keyup"), false, &["enter"])))
+        );
+        assert_eq!(tokens[5].value.as_ref().unwrap(), "submit()");
+    }
+
+    #[test]
+    fn test_recovers_after_invalid_directive() {
+        let input = r#"<a @="x" href="y"></a>"#.to_string();
+        let scanner = Scanner::new(input);
+
+        let (tokens, errors) = scanner.scan_recovering();
+
+        assert_eq!(
+            errors,
+            vec![ScannerError::InvalidDirective("@".to_string(), 3)]
+        );
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[1].value.as_ref().unwrap(), "href");
+        assert_eq!(tokens[2].value.as_ref().unwrap(), "y");
+    }
+
+    #[test]
+    fn test_scans_top_level_blocks_as_raw_text() {
+        let input = r#"<script lang="rust">if a < b { format!("{{}}", a) }</script><template><p>{{ a }}</p></template><i18n>{"<b>": 1}</i18n>"#
+            .to_string();
+        let scanner = Scanner::new(input).sfc();
+
+        let tokens: Vec<Token> = scanner.try_into().unwrap();
+        let scanned: Vec<(TokenType, &str)> = tokens
+            .iter()
+            .filter(|t| t.token_type != TokenType::AttributeValue)
+            .map(|t| (t.token_type.clone(), t.value.as_deref().unwrap()))
+            .collect();
+
+        assert_eq!(
+            scanned,
+            vec![
+                (TokenType::TagOpen, "script"),
+                (TokenType::Attribute, "lang"),
+                (TokenType::TextNode, r#"if a < b { format!("{{}}", a) }"#),
+                (TokenType::TagClose, "script"),
+                (TokenType::TagOpen, "template"),
+                (TokenType::TagOpen, "p"),
+                (TokenType::Interpolation, "a"),
+                (TokenType::TagClose, "p"),
+                (TokenType::TagClose, "template"),
+                (TokenType::TagOpen, "i18n"),
+                (TokenType::TextNode, r#"{"<b>": 1}"#),
+                (TokenType::TagClose, "i18n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skips_directives_and_interpolation_in_v_pre() {
+        let input =
+            r#"<div :a="b" v-pre @c="d"><p :e="f">{{ raw }}</p><br v-pre/></div><i>{{ g }}</i>"#
+                .to_string();
+        let scanner = Scanner::new(input);
+
+        let tokens: Vec<Token> = scanner.try_into().unwrap();
+        let scanned: Vec<(TokenType, &str)> = tokens
+            .iter()
+            .filter(|t| t.token_type != TokenType::AttributeValue)
+            .map(|t| (t.token_type.clone(), t.value.as_deref().unwrap()))
+            .collect();
+
+        assert_eq!(
+            scanned,
+            vec![
+                (TokenType::TagOpen, "div"),
+                (TokenType::Attribute, ":a"),
+                (
+                    TokenType::Directive(Box::new(directive("pre", None, false, &[]))),
+                    "v-pre"
+                ),
+                (TokenType::Attribute, "@c"),
+                (TokenType::TagOpen, "p"),
+                (TokenType::Attribute, ":e"),
+                (TokenType::TextNode, "{{ raw }}"),
+                (TokenType::TagClose, "p"),
+                (TokenType::TagOpen, "br"),
+                (TokenType::Attribute, "v-pre"),
+                (TokenType::TagClose, "br"),
+                (TokenType::TagClose, "div"),
+                (TokenType::TagOpen, "i"),
+                (TokenType::Interpolation, "g"),
+                (TokenType::TagClose, "i"),
+            ]
+        );
     }
 }
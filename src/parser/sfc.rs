@@ -0,0 +1,245 @@
+use std::ops::Range;
+
+use thiserror::Error;
+
+use super::parser::{Node, NodeType};
+
+#[derive(Error, Debug, PartialEq)]
+pub(crate) enum SfcError {
+    #[error("Single file component contains more than one <{0}> block")]
+    DuplicateBlock(String),
+
+    #[error("Single file component has no <template> block")]
+    MissingTemplate,
+}
+
+/// A top level block of a single file component such as `<script>`, `<style>`
+/// or a custom block like `<i18n>`
+#[derive(Debug, PartialEq)]
+pub(crate) struct SfcBlock {
+    pub(crate) tag: String,
+    /// Attributes of the block tag, e.g. `lang`, `scoped` or `src`
+    pub(crate) attributes: Vec<(String, Option<String>)>,
+    /// The raw, unparsed content of the block
+    pub(crate) content: String,
+    pub(crate) span: Range<usize>,
+}
+
+impl SfcBlock {
+    /// Returns the value of an attribute, `Some("")` for attributes without value
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_deref().unwrap_or_default())
+    }
+
+    pub(crate) fn lang(&self) -> Option<&str> {
+        self.attribute("lang")
+    }
+}
+
+/// The `<template>` block of a single file component
+#[derive(Debug, PartialEq)]
+pub(crate) struct SfcTemplate {
+    /// Attributes of the block tag such as `delimiters`, which are not rendered
+    pub(crate) attributes: Vec<(String, Option<String>)>,
+    /// The `<template>` tag node without its attributes, its children are the
+    /// content to render
+    pub(crate) root: Node,
+    pub(crate) span: Range<usize>,
+}
+
+/// The blocks of a single file component, split by their type
+#[derive(Debug, PartialEq, Default)]
+pub(crate) struct SfcDescriptor {
+    pub(crate) template: Option<SfcTemplate>,
+    pub(crate) script: Option<SfcBlock>,
+    /// A `<script setup>` block, which may appear next to a plain `<script>`
+    pub(crate) script_setup: Option<SfcBlock>,
+    pub(crate) styles: Vec<SfcBlock>,
+    pub(crate) custom_blocks: Vec<SfcBlock>,
+}
+
+impl SfcDescriptor {
+    /// Splits the parsed file into its blocks. Text between blocks is ignored.
+    pub(crate) fn from_root(root: Node) -> Result<Self, SfcError> {
+        let mut descriptor = Self::default();
+
+        for node in root.children {
+            let NodeType::Tag(tag) = &node.node_type else {
+                continue;
+            };
+
+            match tag.as_str() {
+                "template" => {
+                    if descriptor.template.is_some() {
+                        return Err(SfcError::DuplicateBlock(tag.clone()));
+                    }
+
+                    let attributes = attributes(&node);
+                    let mut root = node;
+                    root.children.retain(|child| !child.is_attribute());
+
+                    descriptor.template = Some(SfcTemplate {
+                        attributes,
+                        span: root.span.clone(),
+                        root,
+                    });
+                }
+                "script" => {
+                    let block = block(node);
+                    let slot = if block.attribute("setup").is_some() {
+                        &mut descriptor.script_setup
+                    } else {
+                        &mut descriptor.script
+                    };
+
+                    if slot.is_some() {
+                        return Err(SfcError::DuplicateBlock(block.tag));
+                    }
+                    *slot = Some(block);
+                }
+                "style" => descriptor.styles.push(block(node)),
+                _ => descriptor.custom_blocks.push(block(node)),
+            }
+        }
+
+        if descriptor.template.is_none() {
+            return Err(SfcError::MissingTemplate);
+        }
+
+        Ok(descriptor)
+    }
+}
+
+fn attributes(node: &Node) -> Vec<(String, Option<String>)> {
+    node.children
+        .iter()
+        .filter_map(|child| match &child.node_type {
+            NodeType::Attribute(name, value) => Some((
+                name.clone(),
+                value.as_ref().and_then(|token| token.value.clone()),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+fn block(node: Node) -> SfcBlock {
+    let content = node
+        .children
+        .iter()
+        .filter_map(|child| match &child.node_type {
+            NodeType::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    SfcBlock {
+        tag: match &node.node_type {
+            NodeType::Tag(tag) => tag.clone(),
+            _ => String::new(),
+        },
+        attributes: attributes(&node),
+        content,
+        span: node.span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parser::Parser, scanner::Scanner};
+
+    use super::*;
+
+    fn parse(input: &str) -> Result<SfcDescriptor, SfcError> {
+        let scanner = Scanner::new(input.to_string()).sfc();
+        let parser = Parser::new(scanner.try_into().unwrap());
+
+        SfcDescriptor::from_root(parser.try_into().unwrap())
+    }
+
+    #[test]
+    fn test_splits_blocks() {
+        let descriptor = parse(
+            r#"
+            <script lang="rust">let a = 1 < 2;</script>
+            <template><h1>{{ a }}</h1></template>
+            <style scoped>h1 { color: red; }</style>
+            <style src="./theme.css"></style>
+            <i18n lang="json">{"hello": "Hallo"}</i18n>
+            "#,
+        )
+        .unwrap();
+
+        let template = descriptor.template.unwrap();
+        assert_eq!(template.root.children.len(), 1);
+        assert_eq!(
+            template.root.children[0].node_type,
+            NodeType::Tag("h1".to_string())
+        );
+
+        let script = descriptor.script.unwrap();
+        assert_eq!(script.lang(), Some("rust"));
+        assert_eq!(script.content, "let a = 1 < 2;");
+        assert_eq!(script.span, 13..47);
+
+        assert_eq!(descriptor.styles.len(), 2);
+        assert_eq!(descriptor.styles[0].attribute("scoped"), Some(""));
+        assert_eq!(descriptor.styles[0].content, "h1 { color: red; }");
+        assert_eq!(descriptor.styles[1].attribute("src"), Some("./theme.css"));
+        assert_eq!(descriptor.styles[1].attribute("scoped"), None);
+
+        assert_eq!(descriptor.custom_blocks.len(), 1);
+        assert_eq!(descriptor.custom_blocks[0].tag, "i18n");
+        assert_eq!(descriptor.custom_blocks[0].content, r#"{"hello": "Hallo"}"#);
+    }
+
+    #[test]
+    fn test_keeps_template_attributes_out_of_the_content() {
+        let template = parse(r#"<template delimiters="[[ ]]" lang="html"><p/></template>"#)
+            .unwrap()
+            .template
+            .unwrap();
+
+        assert_eq!(
+            template.attributes,
+            vec![
+                ("delimiters".to_string(), Some("[[ ]]".to_string())),
+                ("lang".to_string(), Some("html".to_string())),
+            ]
+        );
+        assert_eq!(template.root.children.len(), 1);
+        assert_eq!(
+            template.root.children[0].node_type,
+            NodeType::Tag("p".to_string())
+        );
+    }
+
+    #[test]
+    fn test_allows_script_next_to_script_setup() {
+        let descriptor =
+            parse("<script>a</script><script setup>b</script><template></template>").unwrap();
+
+        assert_eq!(descriptor.script.unwrap().content, "a");
+        assert_eq!(descriptor.script_setup.unwrap().content, "b");
+    }
+
+    #[test]
+    fn test_rejects_duplicate_blocks() {
+        assert_eq!(
+            parse("<template></template><template></template>"),
+            Err(SfcError::DuplicateBlock("template".to_string()))
+        );
+        assert_eq!(
+            parse("<script>a</script><script>b</script><template></template>"),
+            Err(SfcError::DuplicateBlock("script".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_requires_template() {
+        assert_eq!(parse("<script>a</script>"), Err(SfcError::MissingTemplate));
+    }
+}
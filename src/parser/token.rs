@@ -1,8 +1,20 @@
 use std::fmt::{Display, Formatter};
 
+/// A Vue directive such as `v-on:keyup.enter`, `@click`, `:[key]` or `#header`
+#[derive(Debug, PartialEq, Clone, Default)]
+pub(crate) struct Directive {
+    /// Name without the `v-` prefix. Shorthands are expanded, so `@click` is named `on`
+    pub(crate) name: String,
+    /// Argument after the `:`, for a dynamic argument this is the expression inside `[]`
+    pub(crate) arg: Option<String>,
+    pub(crate) dynamic_arg: bool,
+    pub(crate) modifiers: Vec<String>,
+    pub(crate) value: Option<String>,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum TokenType {
-    Colon,
+    Directive(Box<Directive>),
     Attribute,
     AttributeValue,
     Interpolation,
@@ -26,24 +38,15 @@ impl Token {
             value: Some(value.to_string()),
         }
     }
-
-    pub(crate) fn new(token_type: TokenType, position: usize) -> Self {
-        Self {
-            token_type,
-            position,
-            value: None,
-        }
-    }
 }
 
 impl Display for Token {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let position = format!("@ {}", self.position);
-        let value = self.value.clone().unwrap_or(String::new());
+        let value = self.value.clone().unwrap_or_default();
 
         match self.token_type {
-            TokenType::Colon => write!(f, ":"),
-            TokenType::Attribute => write!(f, "{value}{position}"),
+            TokenType::Directive(_) | TokenType::Attribute => write!(f, "{value}{position}"),
             TokenType::AttributeValue => {
                 write!(f, r#""{value}"{position}"#)
             }
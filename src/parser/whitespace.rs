@@ -0,0 +1,186 @@
+use super::parser::{Node, NodeType};
+
+/// How whitespace in text nodes is treated, mirroring Vue's `whitespace` compiler option
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub(crate) enum WhitespaceMode {
+    /// Drops whitespace-only nodes between elements and collapses whitespace runs
+    #[default]
+    Condense,
+    /// Keeps text as written, only whitespace-only nodes are reduced to a single space
+    Preserve,
+}
+
+impl WhitespaceMode {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "condense" => Some(Self::Condense),
+            "preserve" => Some(Self::Preserve),
+            _ => None,
+        }
+    }
+}
+
+/// Removes and collapses whitespace in the text nodes below `node`. The content
+/// of `<pre>` tags is left untouched.
+pub(crate) fn condense_whitespace(node: &mut Node, mode: WhitespaceMode) {
+    if node.node_type == NodeType::Tag("pre".to_string()) {
+        strip_leading_newline(node);
+        return;
+    }
+
+    // Attributes are children as well, but only content decides what is adjacent
+    let content: Vec<usize> = (0..node.children.len())
+        .filter(|&i| !node.children[i].is_attribute())
+        .collect();
+
+    let mut remove = vec![false; node.children.len()];
+    for (n, &i) in content.iter().enumerate() {
+        let prev = n.checked_sub(1).map(|p| content[p]);
+        let next = content.get(n + 1).copied();
+        let is_tag = |i: usize| matches!(node.children[i].node_type, NodeType::Tag(_));
+        let between_tags = prev.is_some_and(is_tag) && next.is_some_and(is_tag);
+
+        let NodeType::Text(text) = &mut node.children[i].node_type else {
+            continue;
+        };
+
+        if text.chars().all(|c| c.is_ascii_whitespace()) {
+            if prev.is_none()
+                || next.is_none()
+                || (mode == WhitespaceMode::Condense && between_tags && text.contains('\n'))
+            {
+                remove[i] = true;
+            } else {
+                *text = " ".to_string();
+            }
+        } else if mode == WhitespaceMode::Condense {
+            *text = collapse(text);
+        }
+    }
+
+    let mut index = 0;
+    node.children.retain(|_| {
+        index += 1;
+        !remove[index - 1]
+    });
+
+    for child in &mut node.children {
+        condense_whitespace(child, mode);
+    }
+}
+
+/// Collapses every run of whitespace into a single space. Like in Vue only ASCII
+/// whitespace counts, so non-breaking spaces stay as they are.
+fn collapse(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_whitespace = false;
+
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            if !in_whitespace {
+                collapsed.push(' ');
+            }
+            in_whitespace = true;
+        } else {
+            collapsed.push(c);
+            in_whitespace = false;
+        }
+    }
+
+    collapsed
+}
+
+/// Like browsers do, a newline directly after `<pre>` is not part of its content
+fn strip_leading_newline(pre: &mut Node) {
+    let first = pre.children.iter_mut().find(|child| !child.is_attribute());
+
+    if let Some(Node {
+        node_type: NodeType::Text(text),
+        ..
+    }) = first
+    {
+        if let Some(stripped) = text.strip_prefix('\n') {
+            *text = stripped.to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parser::Parser, scanner::Scanner};
+
+    use super::*;
+
+    fn parse(input: &str, mode: WhitespaceMode) -> Node {
+        let scanner = Scanner::new(input.to_string());
+        let parser = Parser::new(scanner.try_into().unwrap());
+        let mut root: Node = parser.try_into().unwrap();
+        condense_whitespace(&mut root, mode);
+
+        root
+    }
+
+    fn texts(node: &Node) -> Vec<String> {
+        let mut found = Vec::new();
+        if let NodeType::Text(text) = &node.node_type {
+            found.push(text.clone());
+        }
+        for child in &node.children {
+            found.extend(texts(child));
+        }
+
+        found
+    }
+
+    #[test]
+    fn test_condense_drops_whitespace_between_elements() {
+        let root = parse(
+            "<template>\n  <h1>Hi</h1>\n  <p>  a \n\t b  </p>\n</template>\n",
+            WhitespaceMode::Condense,
+        );
+
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(root.children[0].children.len(), 2);
+        assert_eq!(texts(&root), vec!["Hi", " a b "]);
+    }
+
+    #[test]
+    fn test_condense_keeps_single_space_between_inline_content() {
+        let root = parse(
+            "<p><b>a</b> <i>b</i> {{ c }}   {{ d }}</p>",
+            WhitespaceMode::Condense,
+        );
+
+        assert_eq!(texts(&root), vec!["a", " ", "b", " ", " "]);
+    }
+
+    #[test]
+    fn test_condense_keeps_non_breaking_spaces() {
+        let root = parse(
+            "<p><b>a</b>\u{a0}<i>b</i>  x\u{a0}\u{a0}\n y</p>",
+            WhitespaceMode::Condense,
+        );
+
+        assert_eq!(texts(&root), vec!["a", "\u{a0}", "b", " x\u{a0}\u{a0} y"]);
+    }
+
+    #[test]
+    fn test_condense_leaves_pre_untouched() {
+        let root = parse(
+            "<div>\n  <pre>\n  a\n\n   b </pre>\n</div>",
+            WhitespaceMode::Condense,
+        );
+
+        assert_eq!(texts(&root), vec!["  a\n\n   b "]);
+    }
+
+    #[test]
+    fn test_preserve_keeps_text_as_written() {
+        let root = parse(
+            "<div>\n  <h1>a  \n b</h1>\n  <p>c</p>\n</div>",
+            WhitespaceMode::Preserve,
+        );
+
+        assert_eq!(texts(&root), vec!["a  \n b", " ", "c"]);
+    }
+}
@@ -0,0 +1,332 @@
+/// Helpers the generated code calls at runtime. A proc macro crate cannot export
+/// them, so they are emitted as `pub mod villain_runtime` next to the generated
+/// `template` function, where user code implementing e.g. `Directive` finds them.
+pub(crate) const RUNTIME: &str = include_str!("runtime/villain_runtime.rs");
+
+/// The `v-html` sanitiser, a separate file so the macro crate can test it
+pub(crate) const SANITIZE: &str = include_str!("runtime/sanitize.rs");
+
+#[cfg(test)]
+mod sanitize;
+
+/// The runtime compiled natively, so its tests can reach the private parts
+#[cfg(test)]
+#[allow(dead_code)]
+mod villain_runtime {
+    include!("runtime/villain_runtime.rs");
+
+    use super::sanitize::SanitizedHtml;
+
+    mod tests {
+        use std::cell::{Cell, RefCell};
+        use std::rc::Rc;
+
+        use futures_signals::signal::{Mutable, SignalExt};
+
+        use super::*;
+
+        /// Subscribes to `value` and returns the values the binding received
+        fn record<R>(value: &R) -> Rc<RefCell<Vec<R::Item>>>
+        where
+            R: Reactive + ?Sized,
+        {
+            let seen = Rc::new(RefCell::new(Vec::new()));
+            let binding = seen.clone();
+            subscribe(value, move |value| binding.borrow_mut().push(value));
+            run_until_stalled();
+
+            seen
+        }
+
+        #[test]
+        fn test_memo_applies_values_when_a_dependency_changes() {
+            let memo = Memo::new();
+            let dependency = Mutable::new(1);
+            memo.depend(&dependency);
+            let value = Mutable::new("a");
+            let seen = record(&Memoized(&value, &memo));
+            assert_eq!(*seen.borrow(), ["a"]);
+
+            value.set("b");
+            run_until_stalled();
+            assert_eq!(*seen.borrow(), ["a"]);
+
+            // Setting the same value is no change
+            dependency.set(1);
+            run_until_stalled();
+            assert_eq!(*seen.borrow(), ["a"]);
+
+            dependency.set(2);
+            run_until_stalled();
+            assert_eq!(*seen.borrow(), ["a", "b"]);
+        }
+
+        #[test]
+        fn test_memoized_value_reads_latest_value() {
+            let memo = Memo::new();
+            let value = Mutable::new(1);
+            let memoized = Memoized(&value, &memo);
+            record(&memoized);
+
+            value.set(2);
+            assert_eq!(memoized.current(), Some(2));
+        }
+
+        #[test]
+        fn test_runs_hooks_in_order_and_cleans_up_on_unmount() {
+            let log = Rc::new(RefCell::new(Vec::new()));
+            let hook = |name: &'static str| {
+                let log = log.clone();
+                move || log.borrow_mut().push(name)
+            };
+            let held = Rc::new(());
+            let parent = {
+                let _parent = Instance::enter();
+                on_mounted(hook("parent mounted"));
+                on_before_unmount(hook("parent before unmount"));
+                on_unmounted(hook("parent unmounted"));
+                {
+                    let _child = Instance::enter();
+                    on_mounted(hook("child mounted"));
+                    on_before_unmount(hook("child before unmount"));
+                    on_unmounted(hook("child unmounted"));
+
+                    let held = held.clone();
+                    spawn(async move {
+                        let _held = held;
+                        std::future::pending::<()>().await;
+                    });
+                }
+                Instance::current().unwrap()
+            };
+            run_until_stalled();
+            assert_eq!(*log.borrow(), ["child mounted", "parent mounted"]);
+            assert_eq!(Rc::strong_count(&held), 2);
+
+            parent.before_unmount();
+            parent.unmount();
+            run_until_stalled();
+            assert_eq!(
+                log.borrow()[2..],
+                [
+                    "parent before unmount",
+                    "child before unmount",
+                    "child unmounted",
+                    "parent unmounted"
+                ]
+            );
+            // The future was cancelled and the hooks dropped with what they hold
+            assert_eq!(Rc::strong_count(&held), 1);
+            assert_eq!(Rc::strong_count(&log), 1);
+        }
+
+        #[test]
+        fn test_error_boundary_unmounts_failed_content_once_rendered() {
+            let failure = Rc::new(Failure::default());
+            let failed = Rc::new(Cell::new(false));
+            let unmounted = Rc::new(Cell::new(false));
+            {
+                let flag = failed.clone();
+                let _content = failure.enter(move || flag.set(true));
+                capture_error(Instance::current(), "broken".into());
+                assert!(failed.get());
+
+                // A sibling rendered after the failing child
+                let flag = unmounted.clone();
+                let instance = Instance::current().unwrap();
+                instance
+                    .unmounted
+                    .borrow_mut()
+                    .push(Box::new(move || flag.set(true)));
+            }
+            assert_eq!(failure.error.get_cloned(), "broken");
+            assert!(!unmounted.get());
+
+            failure.unmount();
+            assert!(unmounted.get());
+        }
+
+        #[test]
+        fn test_computed_follows_its_signal() {
+            let count = Mutable::new(1);
+            let doubled = computed(count.signal().map(|count| count * 2));
+            assert_eq!(doubled.get(), 2);
+
+            count.set(2);
+            run_until_stalled();
+            assert_eq!(doubled.get(), 4);
+        }
+
+        #[test]
+        fn test_watch_calls_back_on_changes_or_immediately() {
+            for immediate in [false, true] {
+                let count = Mutable::new(1);
+                let calls = Rc::new(RefCell::new(Vec::new()));
+                let log = calls.clone();
+                let options = WatchOptions {
+                    immediate,
+                    ..WatchOptions::default()
+                };
+                watch(&count, options, move |value, previous| {
+                    log.borrow_mut().push((*value, previous.copied()));
+                });
+                run_until_stalled();
+
+                count.set(2);
+                run_until_stalled();
+                let expected = if immediate {
+                    vec![(1, None), (2, Some(1))]
+                } else {
+                    vec![(2, Some(1))]
+                };
+                assert_eq!(*calls.borrow(), expected, "immediate: {immediate}");
+            }
+        }
+
+        #[test]
+        fn test_watch_flush_orders_callbacks_around_bindings() {
+            for (flush, expected) in [
+                (Flush::Pre, ["watch", "bind"]),
+                (Flush::Post, ["bind", "watch"]),
+            ] {
+                let count = Mutable::new(1);
+                let log = Rc::new(RefCell::new(Vec::new()));
+                let watcher = log.clone();
+                let options = WatchOptions {
+                    flush,
+                    ..WatchOptions::default()
+                };
+                watch(&count, options, move |_, _| {
+                    watcher.borrow_mut().push("watch")
+                });
+                // The bindings of the template subscribe after the script
+                let binding = log.clone();
+                subscribe(&count, move |_| binding.borrow_mut().push("bind"));
+                run_until_stalled();
+                log.borrow_mut().clear();
+
+                count.set(2);
+                run_until_stalled();
+                assert_eq!(*log.borrow(), expected, "{flush:?}");
+            }
+        }
+
+        const THEME: InjectionKey<&str> = InjectionKey::new("theme");
+        const THEME_ID: InjectionKey<u32> = InjectionKey::new("theme");
+
+        #[test]
+        fn test_inject_finds_the_nearest_provider() {
+            let _root = Instance::enter();
+            provide(&THEME, "dark");
+            provide(&THEME_ID, 7);
+            // Components do not inject their own values
+            assert_eq!(try_inject(&THEME), None);
+
+            let _middle = Instance::enter();
+            let _child = Instance::enter();
+            assert_eq!(inject(&THEME), "dark");
+            assert_eq!(inject(&THEME_ID), 7);
+
+            provide(&THEME, "light");
+            let _grandchild = Instance::enter();
+            assert_eq!(inject(&THEME), "light");
+        }
+
+        #[test]
+        fn test_inject_handles_missing_keys() {
+            let _root = Instance::enter();
+            let _child = Instance::enter();
+            assert_eq!(try_inject(&THEME), None);
+            assert_eq!(inject_or(&THEME, "light"), "light");
+
+            let missing = std::panic::catch_unwind(|| inject(&THEME)).unwrap_err();
+            assert_eq!(
+                missing.downcast_ref::<String>().unwrap(),
+                "No component above provides a value for the injection key `theme`"
+            );
+        }
+
+        #[test]
+        fn test_keep_alive_evicts_least_recently_used_components() {
+            let mut cache = KeepAliveCache(Vec::new());
+            for name in ["A", "B", "C"] {
+                cache.keep(name);
+            }
+
+            // Activating B again makes A the least recently used one
+            let b = cache.take(|name| *name == "B").unwrap();
+            cache.keep(b);
+            assert_eq!(cache.evict(3), ["A"]);
+            assert_eq!(cache.evict(3), Vec::<&str>::new());
+
+            // Only the active component fits
+            assert_eq!(cache.evict(1), ["C", "B"]);
+            assert_eq!(cache.take(|_| true), None);
+        }
+    }
+}
+
+/// Wraps the runtime into the module the generated code refers to
+pub(crate) fn module() -> String {
+    format!(
+        "#[allow(dead_code)]
+        pub mod villain_runtime {{
+            {RUNTIME}
+
+            mod sanitize {{ {SANITIZE} }}
+            pub use sanitize::{{sanitize, SanitizedHtml}};
+        }}"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize::sanitize;
+
+    #[test]
+    fn test_keeps_allowed_markup() {
+        let html = r#"<p class="lead">Hi <b>there</b> &amp; <a href="https://example.com" rel=nofollow>link</a><br></p>"#;
+
+        assert_eq!(
+            sanitize(html).as_str(),
+            r#"<p class="lead">Hi <b>there</b> &amp; <a href="https://example.com" rel="nofollow">link</a><br></p>"#
+        );
+    }
+
+    #[test]
+    fn test_removes_scripts_and_handlers() {
+        let html = r#"<div onclick="steal()">a<script>alert("<p>")</script><STYLE>p{}</STYLE>b<!-- c --></div>"#;
+
+        assert_eq!(sanitize(html).as_str(), "<div>ab</div>");
+    }
+
+    #[test]
+    fn test_unwraps_unknown_elements() {
+        assert_eq!(
+            sanitize("<form action=x><blink>text</blink> 1 < 2</form>").as_str(),
+            "text 1 &lt; 2"
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsafe_urls() {
+        for url in [
+            "javascript:alert(1)",
+            "JaVaScRiPt:alert(1)",
+            "java\tscript:alert(1)",
+            "jav&#x61;script&#x3a;alert(1)",
+            "data:text/html,x",
+        ] {
+            assert_eq!(
+                sanitize(&format!(r#"<a href="{url}">x</a>"#)).as_str(),
+                "<a>x</a>",
+                "{url}"
+            );
+        }
+
+        assert_eq!(
+            sanitize(r#"<img src="/a.png?x=1&y=2" alt='"quoted"'>"#).as_str(),
+            r#"<img src="/a.png?x=1&y=2" alt="&quot;quoted&quot;">"#
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! Allow-list HTML sanitiser for `v-html`.
+//!
+//! Only depends on `std`, so the macro crate compiles it for its tests while
+//! every expansion embeds it into `villain_runtime`.
+
+/// HTML that passed the allow-list of [`sanitize`]. It can only be created by
+/// `sanitize`, so `v-html` never receives unchecked strings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SanitizedHtml(String);
+
+impl SanitizedHtml {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Elements that are kept, all others are removed
+const ALLOWED_ELEMENTS: [&str; 47] = [
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "cite",
+    "code",
+    "dd",
+    "del",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "s",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "th",
+    "thead",
+    "tr",
+    "u",
+    "ul",
+];
+
+/// Attributes allowed on every element in `ALLOWED_ELEMENTS`
+const GLOBAL_ATTRIBUTES: [&str; 4] = ["class", "dir", "lang", "title"];
+
+/// Element specific attributes
+const ELEMENT_ATTRIBUTES: [(&str, &str); 11] = [
+    ("a", "href"),
+    ("a", "rel"),
+    ("img", "src"),
+    ("img", "alt"),
+    ("img", "width"),
+    ("img", "height"),
+    ("ol", "start"),
+    ("td", "colspan"),
+    ("td", "rowspan"),
+    ("th", "colspan"),
+    ("th", "rowspan"),
+];
+
+/// Attributes holding URLs, which must use one of `ALLOWED_SCHEMES` or be relative
+const URL_ATTRIBUTES: [&str; 2] = ["href", "src"];
+const ALLOWED_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Elements that are removed together with their content
+const DROPPED_WITH_CONTENT: [&str; 13] = [
+    "iframe", "math", "noembed", "noframes", "noscript", "object", "script", "select", "style",
+    "svg", "template", "textarea", "title",
+];
+
+const VOID_ELEMENTS: [&str; 3] = ["br", "hr", "img"];
+
+/// Removes every element, attribute and URL scheme that is not on the allow-list.
+/// Elements that are not allowed are unwrapped, keeping their text, except for
+/// the ones like `<script>` whose content is dropped as well.
+pub fn sanitize(html: &str) -> SanitizedHtml {
+    let mut sanitized = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        sanitized.push_str(&rest[..start].replace('>', "&gt;"));
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        let is_closing = rest.starts_with("</");
+        let name_start = if is_closing { 2 } else { 1 };
+        let starts_tag = rest[name_start..].starts_with(|c: char| c.is_ascii_alphabetic());
+        if !starts_tag {
+            // Doctypes and processing instructions are dropped, a lone `<` is text
+            if rest[1..].starts_with(['!', '?', '/']) {
+                rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+            } else {
+                sanitized.push_str("&lt;");
+                rest = &rest[1..];
+            }
+            continue;
+        }
+
+        let Some(end) = tag_end(rest) else {
+            // An unterminated tag is never completed by the browser either
+            break;
+        };
+        let tag = &rest[name_start..end];
+        rest = &rest[end + 1..];
+
+        let name_end = tag
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(tag.len());
+        let name = tag[..name_end].to_ascii_lowercase();
+
+        if DROPPED_WITH_CONTENT.contains(&name.as_str()) {
+            if !is_closing {
+                rest = skip_element(rest, &name);
+            }
+            continue;
+        }
+        if !ALLOWED_ELEMENTS.contains(&name.as_str()) {
+            continue;
+        }
+
+        if is_closing {
+            if !VOID_ELEMENTS.contains(&name.as_str()) {
+                sanitized.push_str(&format!("</{name}>"));
+            }
+            continue;
+        }
+
+        sanitized.push('<');
+        sanitized.push_str(&name);
+        for (attribute, value) in attributes(&tag[name_end..]) {
+            let allowed = GLOBAL_ATTRIBUTES.contains(&attribute.as_str())
+                || ELEMENT_ATTRIBUTES.contains(&(name.as_str(), attribute.as_str()));
+            if !allowed || (URL_ATTRIBUTES.contains(&attribute.as_str()) && !is_safe_url(&value)) {
+                continue;
+            }
+
+            sanitized.push_str(&format!(
+                " {attribute}=\"{}\"",
+                value.replace('"', "&quot;")
+            ));
+        }
+        sanitized.push('>');
+    }
+    sanitized.push_str(&rest.replace('>', "&gt;"));
+
+    SanitizedHtml(sanitized)
+}
+
+/// Position of the `>` closing the tag at the start of `html`, skipping quoted values
+fn tag_end(html: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in html.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(index),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Skips everything up to and including the end tag of `name`
+fn skip_element<'a>(html: &'a str, name: &str) -> &'a str {
+    let lowercase = html.to_ascii_lowercase();
+    let Some(start) = lowercase.find(&format!("</{name}")) else {
+        return "";
+    };
+
+    html[start..]
+        .find('>')
+        .map_or("", |end| &html[start + end + 1..])
+}
+
+/// Parses the attributes of a tag into lowercase names and their values
+fn attributes(mut input: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+
+    loop {
+        input = input.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if input.is_empty() {
+            return attributes;
+        }
+
+        let name_end = input
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(input.len());
+        let name = input[..name_end].to_ascii_lowercase();
+        input = input[name_end..].trim_start();
+
+        let mut value = "";
+        if let Some(rest) = input.strip_prefix('=') {
+            let rest = rest.trim_start();
+            let (found, remaining) = match rest.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = rest[1..].find(quote).map_or(rest.len(), |end| end + 1);
+                    (&rest[1..end], rest.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            value = found;
+            input = remaining;
+        }
+
+        attributes.push((name, value.to_string()));
+    }
+}
+
+/// Whether a URL is relative or uses an allowed scheme. Browsers decode entities
+/// and drop whitespace in schemes, so a scheme containing either is rejected.
+fn is_safe_url(url: &str) -> bool {
+    let url = url.trim();
+    let prefix_end = url.find(['/', '?', '#']).unwrap_or(url.len());
+    let prefix = &url[..prefix_end];
+
+    match prefix.find(':') {
+        Some(colon) => {
+            let scheme = &prefix[..colon];
+            ALLOWED_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str())
+        }
+        None => !prefix.contains(|c: char| c == '&' || c.is_whitespace() || c.is_control()),
+    }
+}
@@ -0,0 +1,2098 @@
+// Runtime support shared by the generated render functions.
+//
+// This file is not a module of the macro crate. It is emitted into every
+// expansion as `mod villain_runtime`, so it may only use the crates the
+// generated code depends on. The macro crate includes it natively for its
+// tests, so it has no inner attributes.
+
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll, Waker};
+
+use futures_signals::signal::{always, Mutable, ReadOnlyMutable, Signal, SignalExt};
+use wasm_bindgen::{JsCast, JsValue};
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::run_until_stalled;
+#[cfg(not(target_arch = "wasm32"))]
+use native::spawn_local;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_futures::spawn_local;
+
+/// A value templates bind to: either a signal that changes over time or a
+/// constant, which behaves like a signal that never changes
+pub trait Reactive {
+    type Item: 'static;
+
+    fn to_signal(&self) -> Pin<Box<dyn Signal<Item = Self::Item>>>;
+
+    /// The current value, read without subscribing to changes
+    fn current(&self) -> Option<Self::Item> {
+        let mut cx = Context::from_waker(Waker::noop());
+        match self.to_signal().as_mut().poll_change(&mut cx) {
+            Poll::Ready(value) => value,
+            Poll::Pending => None,
+        }
+    }
+
+    /// Whether bindings only use the current value, see [`Once`]
+    fn is_once(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Clone + 'static> Reactive for Mutable<T> {
+    type Item = T;
+
+    fn to_signal(&self) -> Pin<Box<dyn Signal<Item = T>>> {
+        Box::pin(self.signal_cloned())
+    }
+
+    fn current(&self) -> Option<T> {
+        Some(self.get_cloned())
+    }
+}
+
+impl<T: Clone + 'static> Reactive for ReadOnlyMutable<T> {
+    type Item = T;
+
+    fn to_signal(&self) -> Pin<Box<dyn Signal<Item = T>>> {
+        Box::pin(self.signal_cloned())
+    }
+
+    fn current(&self) -> Option<T> {
+        Some(self.get_cloned())
+    }
+}
+
+impl<T: Reactive + ?Sized> Reactive for &T {
+    type Item = T::Item;
+
+    fn to_signal(&self) -> Pin<Box<dyn Signal<Item = T::Item>>> {
+        (**self).to_signal()
+    }
+
+    fn current(&self) -> Option<T::Item> {
+        (**self).current()
+    }
+
+    fn is_once(&self) -> bool {
+        (**self).is_once()
+    }
+}
+
+impl Reactive for str {
+    type Item = String;
+
+    fn to_signal(&self) -> Pin<Box<dyn Signal<Item = String>>> {
+        Box::pin(always(self.to_string()))
+    }
+
+    fn current(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+macro_rules! constant {
+    ($($ty:ty),*) => {
+        $(
+            impl Reactive for $ty {
+                type Item = $ty;
+
+                fn to_signal(&self) -> Pin<Box<dyn Signal<Item = $ty>>> {
+                    Box::pin(always(self.clone()))
+                }
+
+                fn current(&self) -> Option<$ty> {
+                    Some(self.clone())
+                }
+            }
+        )*
+    };
+}
+
+constant!(
+    bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64,
+    String, SanitizedHtml, (), Component
+);
+
+/// Uses only the current value of a reactive value, e.g. inside `v-once`.
+/// Bindings read it once without subscribing, so later changes are not rendered.
+pub struct Once<'a, R: ?Sized>(pub &'a R);
+
+impl<R: Reactive + ?Sized> Reactive for Once<'_, R> {
+    type Item = R::Item;
+
+    fn to_signal(&self) -> Pin<Box<dyn Signal<Item = R::Item>>> {
+        Box::pin(First(Some(self.0.to_signal())))
+    }
+
+    fn current(&self) -> Option<R::Item> {
+        self.0.current()
+    }
+
+    fn is_once(&self) -> bool {
+        true
+    }
+}
+
+/// A signal ending after the first value of the wrapped signal
+struct First<T>(Option<Pin<Box<dyn Signal<Item = T>>>>);
+
+impl<T> Signal for First<T> {
+    type Item = T;
+
+    fn poll_change(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        let Some(signal) = self.0.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        match signal.as_mut().poll_change(cx) {
+            Poll::Pending => Poll::Pending,
+            ready => {
+                self.0 = None;
+                ready
+            }
+        }
+    }
+}
+
+/// Counts the changes of the dependencies of a `v-memo`
+pub struct Memo(Mutable<u64>);
+
+impl Memo {
+    pub fn new() -> Self {
+        Self(Mutable::new(0))
+    }
+
+    /// Adds a dependency. Whenever it changes to a different value, the bindings
+    /// of the memoized subtree apply their latest values.
+    pub fn depend<R>(&self, value: &R)
+    where
+        R: Reactive + ?Sized,
+        R::Item: PartialEq,
+    {
+        let revision = self.0.clone();
+        let mut previous: Option<R::Item> = None;
+        subscribe(value, move |value| {
+            if previous.as_ref().is_some_and(|previous| *previous != value) {
+                revision.replace_with(|revision| *revision + 1);
+            }
+            previous = Some(value);
+        });
+    }
+}
+
+/// A reactive value inside `v-memo`, which only changes when the dependencies
+/// of the memo do
+pub struct Memoized<'a, R: ?Sized>(pub &'a R, pub &'a Memo);
+
+impl<R> Reactive for Memoized<'_, R>
+where
+    R: Reactive + ?Sized,
+    R::Item: Clone,
+{
+    type Item = R::Item;
+
+    fn to_signal(&self) -> Pin<Box<dyn Signal<Item = R::Item>>> {
+        Box::pin(Gate {
+            value: self.0.to_signal(),
+            revision: Box::pin(self.1 .0.signal()),
+            latest: None,
+            revision_seen: None,
+            emitted: false,
+        })
+    }
+
+    fn current(&self) -> Option<R::Item> {
+        self.0.current()
+    }
+}
+
+/// Keeps track of the latest value of a signal, but only emits it initially
+/// and whenever the revision changes
+struct Gate<T> {
+    value: Pin<Box<dyn Signal<Item = T>>>,
+    revision: Pin<Box<dyn Signal<Item = u64>>>,
+    latest: Option<T>,
+    revision_seen: Option<u64>,
+    emitted: bool,
+}
+
+// The signals are pinned in their boxes and `latest` is never pinned
+impl<T> Unpin for Gate<T> {}
+
+impl<T: Clone> Signal for Gate<T> {
+    type Item = T;
+
+    fn poll_change(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        let this = &mut *self;
+
+        let mut value_ended = false;
+        loop {
+            match this.value.as_mut().poll_change(cx) {
+                Poll::Ready(Some(value)) => this.latest = Some(value),
+                Poll::Ready(None) => {
+                    value_ended = true;
+                    break;
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        let mut changed = false;
+        let mut revision_ended = false;
+        loop {
+            match this.revision.as_mut().poll_change(cx) {
+                Poll::Ready(Some(revision)) => {
+                    changed |= this.revision_seen.is_some_and(|seen| seen != revision);
+                    this.revision_seen = Some(revision);
+                }
+                Poll::Ready(None) => {
+                    revision_ended = true;
+                    break;
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        match &this.latest {
+            Some(latest) if !this.emitted || changed => {
+                this.emitted = true;
+                Poll::Ready(Some(latest.clone()))
+            }
+            _ if value_ended && revision_ended => Poll::Ready(None),
+            _ => Poll::Pending,
+        }
+    }
+}
+
+/// Runs the binding `f` with the current value of `value` and again after every
+/// change, until the current component unmounts. Changes after the first value
+/// run the `on_updated` hooks, and a panic in `f` is reported to the error
+/// boundaries. Inside `v-once` `f` only runs with the current value, without
+/// subscribing.
+pub fn subscribe<R: Reactive + ?Sized>(value: &R, mut f: impl FnMut(R::Item) + 'static) {
+    let instance = Instance::current();
+    if value.is_once() {
+        if let Some(value) = value.current() {
+            guarded(instance.as_ref(), || {
+                f(value);
+                Ok(())
+            });
+        }
+        return;
+    }
+
+    let mut first = true;
+    spawn(value.to_signal().for_each(move |value| {
+        guarded(instance.as_ref(), || {
+            f(value);
+            Ok(())
+        });
+        if !std::mem::replace(&mut first, false) {
+            if let Some(instance) = &instance {
+                instance.queue_updated();
+            }
+        }
+        async {}
+    }));
+}
+
+/// Caches the values of `signal`, usually derived from other signals with
+/// `map_ref!`, like a `computed` in Vue. The result can be interpolated and
+/// updates until the current component unmounts.
+///
+/// The signal has to have a value right away, which signals of `Mutable`s do.
+pub fn computed<S>(signal: S) -> ReadOnlyMutable<S::Item>
+where
+    S: Signal + 'static,
+    S::Item: 'static,
+{
+    let mut signal = Box::pin(signal);
+    let mut cx = Context::from_waker(Waker::noop());
+    let Poll::Ready(Some(initial)) = signal.as_mut().poll_change(&mut cx) else {
+        panic!("The signal of `computed` has no current value");
+    };
+
+    let value = Mutable::new(initial);
+    let cached = value.clone();
+    let instance = Instance::current();
+    spawn(signal.for_each(move |item| {
+        guarded(instance.as_ref(), || {
+            cached.set(item);
+            Ok(())
+        });
+        async {}
+    }));
+
+    value.read_only()
+}
+
+/// When the callback of [`watch`] runs relative to the DOM updates
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Flush {
+    /// Right when the source changes
+    #[default]
+    Pre,
+    /// After the bindings have applied the same change to the DOM
+    Post,
+}
+
+/// The options of [`watch`]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WatchOptions {
+    /// Whether the callback also runs with the current value of the source
+    pub immediate: bool,
+    pub flush: Flush,
+}
+
+/// Calls `callback` with the new and the previous value whenever `source`
+/// changes, until the current component unmounts. With `immediate` it is first
+/// called with the current value and no previous one.
+pub fn watch<R>(
+    source: &R,
+    options: WatchOptions,
+    mut callback: impl FnMut(&R::Item, Option<&R::Item>) + 'static,
+) where
+    R: Reactive + ?Sized,
+    R::Item: Clone,
+{
+    let mut previous: Option<R::Item> = None;
+    watch_signal(source.to_signal(), options.flush, move |value| {
+        let old = previous.replace(value.clone());
+        if old.is_some() || options.immediate {
+            callback(&value, old.as_ref());
+        }
+    });
+}
+
+/// Runs `effect` with the current value of `signal`, usually combining several
+/// sources with `map_ref!`, and again after every change, like `watchEffect`
+pub fn watch_effect<S>(signal: S, effect: impl FnMut(S::Item) + 'static)
+where
+    S: Signal + 'static,
+    S::Item: 'static,
+{
+    watch_signal(signal, Flush::Pre, effect);
+}
+
+/// Like [`watch_effect`], but runs after the DOM was updated like `watchPostEffect`
+pub fn watch_post_effect<S>(signal: S, effect: impl FnMut(S::Item) + 'static)
+where
+    S: Signal + 'static,
+    S::Item: 'static,
+{
+    watch_signal(signal, Flush::Post, effect);
+}
+
+fn watch_signal<S>(signal: S, flush: Flush, effect: impl FnMut(S::Item) + 'static)
+where
+    S: Signal + 'static,
+    S::Item: 'static,
+{
+    let instance = Instance::current();
+    let effect = Rc::new(RefCell::new(effect));
+    spawn(signal.for_each(move |value| {
+        let instance = instance.clone();
+        let effect = effect.clone();
+        let run = move || {
+            guarded(instance.as_ref(), || {
+                (effect.borrow_mut())(value);
+                Ok(())
+            });
+        };
+
+        match flush {
+            Flush::Pre => run(),
+            // Queued behind the bindings woken by the same change
+            Flush::Post => spawn_local(async move { run() }),
+        }
+        async {}
+    }));
+}
+
+/// Runs a future like `spawn_local` until the current component unmounts
+pub fn spawn(future: impl Future<Output = ()> + 'static) {
+    let subscription = Rc::new(Subscription::default());
+    if let Some(instance) = Instance::current() {
+        instance
+            .subscriptions
+            .borrow_mut()
+            .push(subscription.clone());
+    }
+
+    spawn_local(Cancellable {
+        subscription,
+        future: Box::pin(future),
+    });
+}
+
+/// Adds an event listener, which is removed when the current component
+/// unmounts. Errors of `handler` are reported like the ones of [`handler`].
+pub fn listen(
+    target: &web_sys::EventTarget,
+    event: &'static str,
+    handler: impl FnMut(web_sys::Event) -> Result<(), Error> + 'static,
+) {
+    type Listener = wasm_bindgen::closure::Closure<dyn FnMut(web_sys::Event)>;
+    let closure = Listener::new(self::handler(handler));
+    target
+        .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+        .unwrap();
+
+    let Some(instance) = Instance::current() else {
+        closure.forget();
+        return;
+    };
+    let target = target.clone();
+    let mut closure = Some(closure);
+    instance.unmounted.borrow_mut().push(Box::new(move || {
+        if let Some(closure) = closure.take() {
+            target
+                .remove_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+                .unwrap();
+        }
+    }));
+}
+
+/// Cancels a future of a component when it unmounts
+#[derive(Default)]
+struct Subscription {
+    cancelled: Cell<bool>,
+    waker: RefCell<Option<Waker>>,
+}
+
+impl Subscription {
+    fn cancel(&self) {
+        self.cancelled.set(true);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A future that ends early, dropping what it holds, once it is cancelled
+struct Cancellable<F> {
+    subscription: Rc<Subscription>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future<Output = ()>> Future for Cancellable<F> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.subscription.cancelled.get() {
+            return Poll::Ready(());
+        }
+        *self.subscription.waker.borrow_mut() = Some(cx.waker().clone());
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Sets the attribute `name` to the current value of `value`
+pub fn bind_attribute<R>(element: &web_sys::Element, name: &str, value: &R)
+where
+    R: Reactive + ?Sized,
+    R::Item: ToString,
+{
+    let element = element.clone();
+    let name = name.to_string();
+    subscribe(value, move |value| {
+        element.set_attribute(&name, &value.to_string()).unwrap();
+    });
+}
+
+/// Sets the DOM property `name` of the element, e.g. `value` or `checked`,
+/// instead of an attribute
+pub fn bind_property<R>(element: &web_sys::Element, name: &str, value: &R)
+where
+    R: Reactive + ?Sized,
+    R::Item: Into<JsValue>,
+{
+    let element = element.clone();
+    let name = JsValue::from_str(name);
+    subscribe(value, move |value| {
+        reflect_set(&element, &name, &value.into()).unwrap();
+    });
+}
+
+#[wasm_bindgen::prelude::wasm_bindgen]
+extern "C" {
+    /// `Reflect.set`, which needs `js_sys` otherwise
+    #[wasm_bindgen(js_namespace = Reflect, js_name = set, catch)]
+    fn reflect_set(target: &JsValue, key: &JsValue, value: &JsValue) -> Result<bool, JsValue>;
+}
+
+/// Adds `class` to the element while `active` is true
+pub fn bind_class_toggle<R>(element: &web_sys::Element, class: &str, active: &R)
+where
+    R: Reactive<Item = bool> + ?Sized,
+{
+    let element = element.clone();
+    let class = class.to_string();
+    subscribe(active, move |active| {
+        element.class_list().toggle_with_force(&class, active).unwrap();
+    });
+}
+
+/// Adds the whitespace separated classes of `classes` to the element and removes
+/// the ones added before that are gone. The static classes in `keep` stay.
+pub fn bind_class_list<R>(
+    element: &web_sys::Element,
+    keep: &'static [&'static str],
+    classes: &R,
+) where
+    R: Reactive + ?Sized,
+    R::Item: ToString,
+{
+    let element = element.clone();
+    let mut added: Vec<String> = Vec::new();
+    subscribe(classes, move |classes| {
+        let classes: Vec<String> = classes
+            .to_string()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+
+        let class_list = element.class_list();
+        for class in &added {
+            if !classes.contains(class) && !keep.contains(&class.as_str()) {
+                class_list.remove_1(class).unwrap();
+            }
+        }
+        for class in &classes {
+            class_list.add_1(class).unwrap();
+        }
+
+        added = classes;
+    });
+}
+
+/// The inline style of an element. `style` is defined for SVG and MathML elements
+/// as well, so the property exists even if the element is no `HtmlElement`.
+fn style(element: &web_sys::Element) -> web_sys::CssStyleDeclaration {
+    element.unchecked_ref::<web_sys::HtmlElement>().style()
+}
+
+/// Sets the style `property` to the current value of `value`
+pub fn bind_style_property<R>(element: &web_sys::Element, property: &str, value: &R)
+where
+    R: Reactive + ?Sized,
+    R::Item: ToString,
+{
+    let style = style(element);
+    let property = property.to_string();
+    subscribe(value, move |value| {
+        style.set_property(&property, &value.to_string()).unwrap();
+    });
+}
+
+/// Applies declarations such as `color: red; margin: 0` and removes the
+/// properties set before that are gone
+pub fn bind_style_text<R>(element: &web_sys::Element, declarations: &R)
+where
+    R: Reactive + ?Sized,
+    R::Item: ToString,
+{
+    let style = style(element);
+    let mut set: Vec<String> = Vec::new();
+    subscribe(declarations, move |declarations| {
+        let declarations: Vec<(String, String)> = declarations
+            .to_string()
+            .split(';')
+            .filter_map(|declaration| declaration.split_once(':'))
+            .map(|(property, value)| (property.trim().to_string(), value.trim().to_string()))
+            .collect();
+
+        for property in &set {
+            if !declarations.iter().any(|(name, _)| name == property) {
+                style.remove_property(property).unwrap();
+            }
+        }
+        for (property, value) in &declarations {
+            style.set_property(property, value).unwrap();
+        }
+
+        set = declarations.into_iter().map(|(property, _)| property).collect();
+    });
+}
+
+/// Hides the element while `visible` is false. The `display` value the element
+/// had when it was first shown or hidden is restored when it shows again. With
+/// `transition` later changes play its enter and leave transitions.
+pub fn bind_show<R>(element: &web_sys::Element, visible: &R, transition: Option<Transition>)
+where
+    R: Reactive<Item = bool> + ?Sized,
+{
+    let element = element.clone();
+    let style = style(&element);
+    let generation = Rc::new(Cell::new(0));
+    let mut original: Option<String> = None;
+    let mut shown: Option<bool> = None;
+    subscribe(visible, move |visible| {
+        if shown == Some(visible) {
+            return;
+        }
+        // The first value is applied without transition, like the initial render
+        let initial = shown.is_none();
+        shown = Some(visible);
+        let original =
+            original.get_or_insert_with(|| style.get_property_value("display").unwrap());
+
+        match &transition {
+            Some(transition) if !initial && visible => {
+                style.set_property("display", original).unwrap();
+                transition.enter(&generation, &element, || {});
+            }
+            Some(transition) if !initial => {
+                let style = style.clone();
+                transition.leave(&generation, &element, move || {
+                    style.set_property("display", "none").unwrap();
+                });
+            }
+            _ => {
+                let display = if visible { original.as_str() } else { "none" };
+                style.set_property("display", display).unwrap();
+            }
+        }
+    });
+}
+
+/// Replaces the content of the element with the current HTML. Only
+/// [`SanitizedHtml`] is accepted, so a plain `String` fails to compile.
+pub fn bind_html<R>(element: &web_sys::Element, html: &R)
+where
+    R: Reactive<Item = SanitizedHtml> + ?Sized,
+{
+    let element = element.clone();
+    subscribe(html, move |html| element.set_inner_html(html.as_str()));
+}
+
+/// Replaces the content of the element with the current text
+pub fn bind_text<R>(element: &web_sys::Element, text: &R)
+where
+    R: Reactive + ?Sized,
+    R::Item: ToString,
+{
+    let element = element.clone();
+    subscribe(text, move |text| element.set_text_content(Some(&text.to_string())));
+}
+
+/// Appends a text node showing the current value of an interpolation
+pub fn bind_text_node<R>(document: &web_sys::Document, parent: &web_sys::Element, value: &R)
+where
+    R: Reactive + ?Sized,
+    R::Item: ToString,
+{
+    let node = document.create_text_node("");
+    parent.append_child(&node).unwrap();
+    subscribe(value, move |value| node.set_data(&value.to_string()));
+}
+
+/// Removes `v-cloak` from the element once the values queued before are applied
+pub fn uncloak(element: &web_sys::Element) {
+    let element = element.clone();
+    spawn_local(async move {
+        element.remove_attribute("v-cloak").unwrap();
+    });
+}
+
+/// What a custom directive is bound to, e.g. for `v-tooltip:top.delay="text"`
+/// the value of `text`, the argument `top` and the modifiers `["delay"]`
+pub struct DirectiveBinding<'a, T> {
+    pub value: &'a T,
+    /// The value before the last change, only set in `updated`
+    pub old_value: Option<&'a T>,
+    pub arg: Option<&'a str>,
+    pub modifiers: &'a [&'a str],
+}
+
+/// A custom directive such as `v-focus`. Directives are registered with the
+/// `directives` option of `create_component!` and `create_entrypoint!`, and a
+/// new instance is created with `Default` for every element using it.
+pub trait Directive: Default + 'static {
+    /// The type of the bound value, `()` for directives used without one
+    type Value: Clone + 'static;
+
+    /// Called with the first value, once the element is rendered
+    fn mounted(&mut self, element: &web_sys::Element, binding: &DirectiveBinding<Self::Value>) {
+        let _ = (element, binding);
+    }
+
+    /// Called whenever the bound value changes
+    fn updated(&mut self, element: &web_sys::Element, binding: &DirectiveBinding<Self::Value>) {
+        let _ = (element, binding);
+    }
+
+    /// Called when the element is removed
+    fn unmounted(&mut self, element: &web_sys::Element, binding: &DirectiveBinding<Self::Value>) {
+        let _ = (element, binding);
+    }
+}
+
+/// Binds the custom directive `D` to the element. Its `unmounted` hook runs when
+/// the current component unmounts.
+pub fn bind_directive<D, R>(
+    element: &web_sys::Element,
+    arg: Option<String>,
+    modifiers: &'static [&'static str],
+    value: &R,
+) where
+    D: Directive,
+    R: Reactive<Item = D::Value> + ?Sized,
+{
+    // The directive and the last value, shared with the `unmounted` hook
+    let state: Rc<RefCell<(D, Option<D::Value>)>> = Rc::default();
+
+    if let Some(instance) = Instance::current() {
+        let element = element.clone();
+        let arg = arg.clone();
+        let state = state.clone();
+        instance.unmounted.borrow_mut().push(Box::new(move || {
+            let (directive, value) = &mut *state.borrow_mut();
+            if let Some(value) = value.as_ref() {
+                let binding = DirectiveBinding {
+                    value,
+                    old_value: None,
+                    arg: arg.as_deref(),
+                    modifiers,
+                };
+                directive.unmounted(&element, &binding);
+            }
+        }));
+    }
+
+    let element = element.clone();
+    subscribe(value, move |value| {
+        let (directive, old_value) = &mut *state.borrow_mut();
+        let binding = DirectiveBinding {
+            value: &value,
+            old_value: old_value.as_ref(),
+            arg: arg.as_deref(),
+            modifiers,
+        };
+
+        if old_value.is_some() {
+            directive.updated(&element, &binding);
+        } else {
+            directive.mounted(&element, &binding);
+        }
+        *old_value = Some(value);
+    });
+}
+
+/// The instance of a rendered component, holding the hooks its script registered
+/// and the instances of the components it rendered
+#[derive(Default)]
+pub struct Instance {
+    parent: Option<Weak<Instance>>,
+    children: RefCell<Vec<Rc<Instance>>>,
+    mounted: RefCell<Vec<Box<dyn FnMut()>>>,
+    updated: RefCell<Vec<Box<dyn FnMut()>>>,
+    activated: RefCell<Vec<Box<dyn FnMut()>>>,
+    deactivated: RefCell<Vec<Box<dyn FnMut()>>>,
+    before_unmount: RefCell<Vec<Box<dyn FnMut()>>>,
+    unmounted: RefCell<Vec<Box<dyn FnMut()>>>,
+    error_captured: RefCell<Vec<ErrorHook>>,
+    /// The innermost `<Suspense>` the component is rendered in
+    suspense: Option<Rc<Suspense>>,
+    /// What the component passed to `expose`, for a `ref` on it
+    exposed: RefCell<Option<Rc<dyn Any>>>,
+    /// The values the component passed to `provide`, for its descendants
+    provides: RefCell<HashMap<InjectionId, Rc<dyn Any>>>,
+    /// The futures of the component, cancelled when it unmounts
+    subscriptions: RefCell<Vec<Rc<Subscription>>>,
+    /// Set once the component is in the document and its `on_mounted` hooks ran
+    is_mounted: Cell<bool>,
+    /// Set while an async component or the content of a `<Suspense>` waits
+    pending: Cell<bool>,
+    updated_queued: Cell<bool>,
+    /// Set while `<KeepAlive>` keeps the component in its cache
+    cached: Cell<bool>,
+}
+
+thread_local! {
+    /// The instances whose render functions are running, innermost last
+    static CURRENT: RefCell<Vec<Rc<Instance>>> = const { RefCell::new(Vec::new()) };
+
+    /// The instances of the entry components, which are never unmounted
+    static ROOTS: RefCell<Vec<Rc<Instance>>> = const { RefCell::new(Vec::new()) };
+}
+
+impl Instance {
+    /// Creates the instance of the component whose render function starts, as a
+    /// child of the current one. It is the current instance until the guard drops.
+    pub fn enter() -> InstanceGuard {
+        let parent = Self::current();
+        let mut guard = Self::push(Self::child_of(parent.as_ref()));
+        // The entry component renders into the document, so it is mounted once
+        // its render function has finished
+        guard.mounts = parent.is_none();
+        guard
+    }
+
+    fn current() -> Option<Rc<Instance>> {
+        CURRENT.with(|current| current.borrow().last().cloned())
+    }
+
+    fn child_of(parent: Option<&Rc<Instance>>) -> Rc<Instance> {
+        Self::child_in(parent, parent.and_then(|parent| parent.suspense.clone()))
+    }
+
+    fn child_in(parent: Option<&Rc<Instance>>, suspense: Option<Rc<Suspense>>) -> Rc<Instance> {
+        let instance = Rc::new(Instance {
+            parent: parent.map(Rc::downgrade),
+            suspense,
+            ..Instance::default()
+        });
+        match parent {
+            Some(parent) => parent.children.borrow_mut().push(instance.clone()),
+            None => ROOTS.with(|roots| roots.borrow_mut().push(instance.clone())),
+        }
+
+        instance
+    }
+
+    fn push(instance: Rc<Instance>) -> InstanceGuard {
+        CURRENT.with(|current| current.borrow_mut().push(instance.clone()));
+        InstanceGuard {
+            instance,
+            mounts: false,
+        }
+    }
+
+    fn parent_is_mounted(&self) -> bool {
+        self.parent
+            .as_ref()
+            .and_then(Weak::upgrade)
+            .is_none_or(|parent| parent.is_mounted.get())
+    }
+
+    /// Runs the `on_mounted` hooks of the descendants and then of this instance,
+    /// skipping the ones that already ran and pending async components
+    fn mount(&self) {
+        if self.pending.get() {
+            return;
+        }
+        let children = self.children.borrow().clone();
+        for child in children {
+            child.mount();
+        }
+        if !self.is_mounted.replace(true) {
+            run_hooks(&self.mounted);
+        }
+    }
+
+    /// Runs the `on_updated` hooks once the current changes have been applied
+    fn queue_updated(self: &Rc<Self>) {
+        if !self.is_mounted.get() || self.updated_queued.replace(true) {
+            return;
+        }
+
+        let instance = self.clone();
+        spawn_local(async move {
+            instance.updated_queued.set(false);
+            if instance.is_mounted.get() {
+                run_hooks(&instance.updated);
+            }
+        });
+    }
+
+    /// Runs the `on_before_unmount` hooks of this instance and then of its
+    /// descendants, while their nodes are still in the document
+    fn before_unmount(&self) {
+        run_hooks(&self.before_unmount);
+        let children = self.children.borrow().clone();
+        for child in children {
+            child.before_unmount();
+        }
+    }
+
+    fn remove_child(&self, child: &Rc<Instance>) {
+        self.children
+            .borrow_mut()
+            .retain(|instance| !Rc::ptr_eq(instance, child));
+    }
+
+    /// Runs the `activated` hooks of this instance and its descendants, skipping
+    /// the ones an inner `<KeepAlive>` has cached
+    fn activate(&self) {
+        let children = self.children.borrow().clone();
+        for child in children {
+            if !child.cached.get() {
+                child.activate();
+            }
+        }
+        run_hooks(&self.activated);
+    }
+
+    /// Runs the `deactivated` hooks like `activate` runs the `activated` ones
+    fn deactivate(&self) {
+        let children = self.children.borrow().clone();
+        for child in children {
+            if !child.cached.get() {
+                child.deactivate();
+            }
+        }
+        run_hooks(&self.deactivated);
+    }
+
+    /// Unmounts the descendants and then this instance: its subscriptions end,
+    /// the `on_unmounted` hooks run, and all hooks are dropped with what they hold
+    fn unmount(&self) {
+        let children = self.children.take();
+        for child in children {
+            child.unmount();
+        }
+
+        for subscription in self.subscriptions.take() {
+            subscription.cancel();
+        }
+        self.is_mounted.set(false);
+        run_hooks(&self.unmounted);
+        for hooks in [
+            &self.mounted,
+            &self.updated,
+            &self.activated,
+            &self.deactivated,
+            &self.before_unmount,
+            &self.unmounted,
+        ] {
+            hooks.take();
+        }
+        self.error_captured.take();
+        self.exposed.take();
+        self.provides.take();
+    }
+}
+
+/// Keeps an instance current while its component renders
+pub struct InstanceGuard {
+    instance: Rc<Instance>,
+    /// Whether the instance is mounted when the guard drops
+    mounts: bool,
+}
+
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.borrow_mut().pop());
+        if self.mounts {
+            self.instance.mount();
+        }
+    }
+}
+
+fn run_hooks(hooks: &RefCell<Vec<Box<dyn FnMut()>>>) {
+    // Taken out while running, so a hook may register further hooks
+    let mut running = hooks.take();
+    for hook in &mut running {
+        hook();
+    }
+    running.append(&mut hooks.borrow_mut());
+    *hooks.borrow_mut() = running;
+}
+
+fn current_instance(hook: &str) -> Rc<Instance> {
+    Instance::current()
+        .unwrap_or_else(|| panic!("`{hook}` can only be called while a component renders"))
+}
+
+/// Registers a hook that runs once the component and the ones it renders are
+/// in the document
+pub fn on_mounted(hook: impl FnMut() + 'static) {
+    current_instance("on_mounted")
+        .mounted
+        .borrow_mut()
+        .push(Box::new(hook));
+}
+
+/// Registers a hook that runs after changes of the state of the component
+/// have been applied to its DOM
+pub fn on_updated(hook: impl FnMut() + 'static) {
+    current_instance("on_updated")
+        .updated
+        .borrow_mut()
+        .push(Box::new(hook));
+}
+
+/// Registers a hook that runs before the component is removed, while its
+/// nodes are still in the document
+pub fn on_before_unmount(hook: impl FnMut() + 'static) {
+    current_instance("on_before_unmount")
+        .before_unmount
+        .borrow_mut()
+        .push(Box::new(hook));
+}
+
+/// Registers a hook that runs after the component is removed and its
+/// subscriptions have ended
+pub fn on_unmounted(hook: impl FnMut() + 'static) {
+    current_instance("on_unmounted")
+        .unmounted
+        .borrow_mut()
+        .push(Box::new(hook));
+}
+
+/// Registers a hook that runs when `<KeepAlive>` first mounts the component or
+/// one rendering it, and whenever it reinserts it from its cache
+pub fn on_activated(hook: impl FnMut() + 'static) {
+    current_instance("on_activated")
+        .activated
+        .borrow_mut()
+        .push(Box::new(hook));
+}
+
+/// Registers a hook that runs when `<KeepAlive>` moves the component or one
+/// rendering it into its cache
+pub fn on_deactivated(hook: impl FnMut() + 'static) {
+    current_instance("on_deactivated")
+        .deactivated
+        .borrow_mut()
+        .push(Box::new(hook));
+}
+
+/// Registers a hook that runs when the component or one it renders reports an
+/// error. Returning `false` stops the error from reaching outer components.
+pub fn on_error_captured(hook: impl FnMut(&Error) -> bool + 'static) {
+    current_instance("on_error_captured")
+        .error_captured
+        .borrow_mut()
+        .push(Box::new(hook));
+}
+
+/// An error of a component, reported to the `on_error_captured` hooks of it and
+/// the components around it
+pub type Error = Box<dyn std::error::Error>;
+
+/// A hook registered with [`on_error_captured`]
+type ErrorHook = Box<dyn FnMut(&Error) -> bool>;
+
+/// Runs `f`, which belongs to the component of `instance`. A returned error,
+/// and a panic where panics unwind, is reported to its error boundaries.
+fn guarded<T>(instance: Option<&Rc<Instance>>, f: impl FnOnce() -> Result<T, Error>) -> Option<T> {
+    let error = match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => return Some(value),
+        Ok(Err(error)) => error,
+        Err(panic) => panic_error(panic),
+    };
+    capture_error(instance.cloned(), error);
+
+    None
+}
+
+fn panic_error(panic: Box<dyn Any + Send>) -> Error {
+    match panic.downcast::<String>() {
+        Ok(message) => (*message).into(),
+        Err(panic) => match panic.downcast_ref::<&str>() {
+            Some(message) => (*message).into(),
+            None => "panicked".into(),
+        },
+    }
+}
+
+/// Calls the `on_error_captured` hooks from the component of `instance` outwards
+/// until one returns `false`. An error nothing stops panics like before.
+fn capture_error(instance: Option<Rc<Instance>>, error: Error) {
+    if !report_error(instance, &error) {
+        panic!("Uncaught error in component: {error}");
+    }
+}
+
+/// Passes `error` to the `on_error_captured` hooks of the component and its
+/// ancestors until one returns `false`, and returns whether one did
+fn report_error(instance: Option<Rc<Instance>>, error: &Error) -> bool {
+    let mut next = instance;
+    while let Some(instance) = next {
+        // Taken out while running, so a hook may register further hooks
+        let mut hooks = instance.error_captured.take();
+        let stopped = hooks.iter_mut().any(|hook| !hook(error));
+        hooks.append(&mut instance.error_captured.borrow_mut());
+        *instance.error_captured.borrow_mut() = hooks;
+        if stopped {
+            return true;
+        }
+
+        next = instance.parent.as_ref().and_then(Weak::upgrade);
+    }
+
+    false
+}
+
+/// Runs the script and template of a component, so the script can use `?`
+pub fn setup(f: impl FnOnce() -> Result<(), Error>) {
+    guarded(Instance::current().as_ref(), f);
+}
+
+/// Wraps an event handler of the current component, reporting its errors
+pub fn handler<E: 'static>(
+    mut f: impl FnMut(E) -> Result<(), Error> + 'static,
+) -> impl FnMut(E) + 'static {
+    let instance = Instance::current();
+    move |event| {
+        guarded(instance.as_ref(), || f(event));
+    }
+}
+
+/// A component rendered by `<component :is="...">`. The components a template
+/// names in its script or in `:is` are available as constants, e.g. `TabA`.
+#[derive(Clone)]
+pub struct Component {
+    name: &'static str,
+    render: Render,
+}
+
+/// Code rendering a component into the given element
+type RenderFn = dyn Fn(&web_sys::Document, web_sys::Element);
+
+#[derive(Clone)]
+enum Render {
+    /// The render function of a component compiled by the macro
+    Static(fn(&web_sys::Document, web_sys::Element)),
+    /// Any other code rendering into the given element
+    Boxed(Rc<RenderFn>),
+}
+
+impl Component {
+    /// A component compiled by the macro, named after its file
+    pub const fn new(name: &'static str, render: fn(&web_sys::Document, web_sys::Element)) -> Self {
+        Self {
+            name,
+            render: Render::Static(render),
+        }
+    }
+
+    pub fn boxed(
+        name: &'static str,
+        render: impl Fn(&web_sys::Document, web_sys::Element) + 'static,
+    ) -> Self {
+        Self {
+            name,
+            render: Render::Boxed(Rc::new(render)),
+        }
+    }
+
+    /// The name `<KeepAlive include exclude>` matches
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Renders the component in front of `anchor` with an instance that is a
+    /// child of `owner`
+    fn render_before(
+        &self,
+        document: &web_sys::Document,
+        anchor: &web_sys::Node,
+        owner: Option<&Rc<Instance>>,
+    ) -> Mounted {
+        let container = document.create_element("div").unwrap();
+        let instance = Instance::child_of(owner);
+        {
+            let _guard = Instance::push(instance.clone());
+            match &self.render {
+                Render::Static(render) => render(document, container.clone()),
+                Render::Boxed(render) => render(document, container.clone()),
+            }
+        }
+
+        let nodes = child_nodes(&container);
+        insert_before(&nodes, anchor);
+
+        Mounted {
+            component: self.clone(),
+            nodes,
+            instance,
+            generation: Rc::default(),
+        }
+    }
+}
+
+impl PartialEq for Component {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.render, &other.render) {
+            (Render::Static(a), Render::Static(b)) => *a as usize == *b as usize,
+            (Render::Boxed(a), Render::Boxed(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// A component rendered by `bind_component`
+#[derive(Clone)]
+struct Mounted {
+    component: Component,
+    nodes: Vec<web_sys::Node>,
+    instance: Rc<Instance>,
+    /// Counts the transitions of the component, see `Transition`
+    generation: Rc<Cell<u64>>,
+}
+
+impl Mounted {
+    /// The element a `<Transition>` animates, the first of the component
+    fn element(&self) -> Option<web_sys::Element> {
+        self.nodes
+            .iter()
+            .find_map(|node| node.dyn_ref::<web_sys::Element>().cloned())
+    }
+
+    fn enter(&self, transition: Option<&Transition>) {
+        if let (Some(transition), Some(element)) = (transition, self.element()) {
+            transition.enter(&self.generation, &element, || {});
+        }
+    }
+
+    /// Calls `done` after the leave transition, right away without one
+    fn leave(&self, transition: Option<&Transition>, done: impl FnOnce() + 'static) {
+        match (transition, self.element()) {
+            (Some(transition), Some(element)) => transition.leave(&self.generation, &element, done),
+            _ => done(),
+        }
+    }
+
+    fn unmount(self, owner: Option<&Rc<Instance>>) {
+        self.instance.before_unmount();
+        remove_nodes(&self.nodes);
+        self.instance.unmount();
+        if let Some(owner) = owner {
+            owner.remove_child(&self.instance);
+        }
+    }
+}
+
+fn child_nodes(element: &web_sys::Element) -> Vec<web_sys::Node> {
+    let children = element.child_nodes();
+    (0..children.length())
+        .filter_map(|index| children.item(index))
+        .collect()
+}
+
+fn insert_before(nodes: &[web_sys::Node], anchor: &web_sys::Node) {
+    let parent = anchor.parent_node().unwrap();
+    for node in nodes {
+        parent.insert_before(node, Some(anchor)).unwrap();
+    }
+}
+
+/// Removes rendered nodes from the document
+fn remove_nodes(nodes: &[web_sys::Node]) {
+    for node in nodes {
+        if let Some(parent) = node.parent_node() {
+            parent.remove_child(node).unwrap();
+        }
+    }
+}
+
+/// The attributes of a `<KeepAlive>`. A component is cached if its name is in
+/// `include`, or `include` is empty, and it is not in `exclude`.
+pub struct KeepAlive {
+    pub include: &'static [&'static str],
+    pub exclude: &'static [&'static str],
+    /// The most components kept at once, including the active one
+    pub max: Option<usize>,
+}
+
+impl KeepAlive {
+    fn caches(&self, component: &Component) -> bool {
+        (self.include.is_empty() || self.include.contains(&component.name))
+            && !self.exclude.contains(&component.name)
+    }
+}
+
+/// The inactive components of a `<KeepAlive>`, least recently used first
+struct KeepAliveCache<T>(Vec<T>);
+
+impl<T> KeepAliveCache<T> {
+    /// Adds a component that became inactive as the most recently used one
+    fn keep(&mut self, entry: T) {
+        self.0.push(entry);
+    }
+
+    /// Takes a component out of the cache to make it active again
+    fn take(&mut self, matches: impl Fn(&T) -> bool) -> Option<T> {
+        let index = self.0.iter().position(matches)?;
+        Some(self.0.remove(index))
+    }
+
+    /// Removes the least recently used components until the rest fit into
+    /// `max` together with the active one
+    fn evict(&mut self, max: usize) -> Vec<T> {
+        let excess = (self.0.len() + 1).saturating_sub(max).min(self.0.len());
+        self.0.drain(..excess).collect()
+    }
+}
+
+/// Renders the current component at this position of `parent`. When it changes
+/// the previous component is removed and the new one rendered in its place.
+///
+/// With `keep_alive` the DOM and state of cached components is detached instead
+/// and reinserted when they become current again. The least recently used ones
+/// are removed when there are more than `max`. With `transition` the components
+/// play its enter and leave transitions.
+pub fn bind_component<R>(
+    document: &web_sys::Document,
+    parent: &web_sys::Element,
+    component: &R,
+    keep_alive: Option<KeepAlive>,
+    transition: Option<Transition>,
+) where
+    R: Reactive<Item = Component> + ?Sized,
+{
+    let document = document.clone();
+    let anchor: web_sys::Node = document.create_comment("component").into();
+    parent.append_child(&anchor).unwrap();
+
+    // Called while the owning component renders, the signal updates later
+    let owner = Instance::current();
+    let mut current: Option<Mounted> = None;
+    let mut cache = KeepAliveCache(Vec::new());
+    subscribe(component, move |component| {
+        if current.as_ref().is_some_and(|mounted| mounted.component == component) {
+            return;
+        }
+        let caches =
+            |component: &Component| keep_alive.as_ref().is_some_and(|k| k.caches(component));
+
+        if let Some(previous) = current.take() {
+            if caches(&previous.component) {
+                previous.instance.deactivate();
+                previous.instance.cached.set(true);
+                let nodes = previous.nodes.clone();
+                previous.leave(transition.as_ref(), move || remove_nodes(&nodes));
+                cache.keep(previous);
+            } else {
+                let leaving = previous.clone();
+                let owner = owner.clone();
+                previous.leave(transition.as_ref(), move || leaving.unmount(owner.as_ref()));
+            }
+        }
+
+        let mounted = match cache.take(|cached| cached.component == component) {
+            Some(mounted) => {
+                insert_before(&mounted.nodes, &anchor);
+                mounted.instance.cached.set(false);
+                mounted.instance.activate();
+                mounted
+            }
+            None => {
+                let mounted = component.render_before(&document, &anchor, owner.as_ref());
+                // Otherwise the owner mounts it together with itself
+                if owner.as_ref().is_none_or(|owner| owner.is_mounted.get()) {
+                    mounted.instance.mount();
+                }
+                if caches(&component) {
+                    mounted.instance.activate();
+                }
+                mounted
+            }
+        };
+        mounted.enter(transition.as_ref());
+        current = Some(mounted);
+
+        if let Some(max) = keep_alive.as_ref().and_then(|k| k.max) {
+            for evicted in cache.evict(max) {
+                evicted.unmount(owner.as_ref());
+            }
+        }
+    });
+}
+
+/// The classes of a `<Transition name="fade">`. Entering elements get
+/// `fade-enter-from` and `fade-enter-active`, two frames later `fade-enter-from`
+/// is swapped for `fade-enter-to`, and both are removed once the transitions and
+/// animations have ended. Leaving works the same with `fade-leave-*`.
+#[derive(Clone, Copy)]
+pub struct Transition {
+    name: &'static str,
+}
+
+/// Events ending a transition
+const TRANSITION_END: [&str; 2] = ["transitionend", "animationend"];
+
+impl Transition {
+    pub const fn new(name: &'static str) -> Self {
+        Self { name }
+    }
+
+    fn enter(
+        &self,
+        generation: &Rc<Cell<u64>>,
+        element: &web_sys::Element,
+        done: impl FnOnce() + 'static,
+    ) {
+        self.run("enter", generation, element, done);
+    }
+
+    fn leave(
+        &self,
+        generation: &Rc<Cell<u64>>,
+        element: &web_sys::Element,
+        done: impl FnOnce() + 'static,
+    ) {
+        self.run("leave", generation, element, done);
+    }
+
+    /// `generation` counts the transitions of the element. Starting one cancels
+    /// the running one, whose `done` is then never called.
+    fn run(
+        &self,
+        stage: &str,
+        generation: &Rc<Cell<u64>>,
+        element: &web_sys::Element,
+        done: impl FnOnce() + 'static,
+    ) {
+        let id = generation.get() + 1;
+        generation.set(id);
+        let is_current = {
+            let generation = generation.clone();
+            move || generation.get() == id
+        };
+
+        let classes = element.class_list();
+        for stage in ["enter", "leave"] {
+            for step in ["from", "active", "to"] {
+                classes.remove_1(&format!("{}-{stage}-{step}", self.name)).unwrap();
+            }
+        }
+        let from = format!("{}-{stage}-from", self.name);
+        let active = format!("{}-{stage}-active", self.name);
+        let to = format!("{}-{stage}-to", self.name);
+        classes.add_2(&from, &active).unwrap();
+
+        let element = element.clone();
+        next_frame(move || {
+            if !is_current() {
+                return;
+            }
+            classes.remove_1(&from).unwrap();
+            classes.add_1(&to).unwrap();
+
+            when_transitioned(&element, move || {
+                if is_current() {
+                    classes.remove_2(&active, &to).unwrap();
+                    done();
+                }
+            });
+        });
+    }
+}
+
+/// Runs `f` on the frame after the next, when the browser has rendered the
+/// styles set before
+fn next_frame(f: impl FnOnce() + 'static) {
+    let window = web_sys::window().unwrap();
+    let inner = window.clone();
+    let outer = wasm_bindgen::closure::Closure::once_into_js(move || {
+        let f = wasm_bindgen::closure::Closure::once_into_js(f);
+        inner.request_animation_frame(f.unchecked_ref()).unwrap();
+    });
+    window.request_animation_frame(outer.unchecked_ref()).unwrap();
+}
+
+/// Calls `done` when the first transition or animation of `element` itself
+/// ends, right away if its computed style has none
+fn when_transitioned(element: &web_sys::Element, done: impl FnOnce() + 'static) {
+    let style = web_sys::window()
+        .unwrap()
+        .get_computed_style(element)
+        .unwrap()
+        .unwrap();
+    let animated = ["transition-duration", "animation-duration"].iter().any(|property| {
+        style
+            .get_property_value(property)
+            .unwrap()
+            .split(',')
+            .any(|duration| {
+                let seconds = duration.trim().trim_end_matches('s').parse::<f64>();
+                seconds.is_ok_and(|seconds| seconds > 0.0)
+            })
+    });
+    if !animated {
+        done();
+        return;
+    }
+
+    type Listener = wasm_bindgen::closure::Closure<dyn FnMut(web_sys::Event)>;
+    let listener: Rc<RefCell<Option<Listener>>> = Rc::default();
+    let mut done = Some(done);
+    let closure = {
+        let element = element.clone();
+        let listener = listener.clone();
+        Listener::new(move |event: web_sys::Event| {
+            // Ends of the children bubble up and are ignored
+            let target: &web_sys::EventTarget = element.as_ref();
+            if event.target().as_ref() != Some(target) {
+                return;
+            }
+            let Some(done) = done.take() else {
+                return;
+            };
+
+            if let Some(closure) = listener.borrow_mut().take() {
+                let callback = closure.as_ref().unchecked_ref();
+                for event in TRANSITION_END {
+                    element
+                        .remove_event_listener_with_callback(event, callback)
+                        .unwrap();
+                }
+                // Dropped after this call returns, not while it runs
+                spawn_local(async move { drop(closure) });
+            }
+            done();
+        })
+    };
+    for event in TRANSITION_END {
+        element
+            .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+            .unwrap();
+    }
+    *listener.borrow_mut() = Some(closure);
+}
+
+/// The content of a `<Teleport>`, which renders into a detached container and is
+/// then moved to the element matching `to`, or in place while disabled
+pub struct Teleport {
+    document: web_sys::Document,
+    to: String,
+    container: web_sys::Element,
+    anchor: web_sys::Node,
+}
+
+impl Teleport {
+    pub fn new(document: &web_sys::Document, parent: &web_sys::Element, to: &str) -> Self {
+        let anchor: web_sys::Node = document.create_comment("teleport").into();
+        parent.append_child(&anchor).unwrap();
+
+        Self {
+            document: document.clone(),
+            to: to.to_string(),
+            container: document.create_element("div").unwrap(),
+            anchor,
+        }
+    }
+
+    /// The element the content renders into
+    pub fn container(&self) -> web_sys::Element {
+        self.container.clone()
+    }
+
+    /// Moves the rendered content to the target, or in front of the anchor while
+    /// `disabled` is true. It is removed when the owning component unmounts.
+    ///
+    /// A target that does not exist is reported to the `on_error_captured` hooks,
+    /// and the content stays in place like with `disabled`.
+    pub fn mount<R>(self, disabled: &R)
+    where
+        R: Reactive<Item = bool> + ?Sized,
+    {
+        let nodes = child_nodes(&self.container);
+        let instance = Instance::current();
+
+        if let Some(owner) = &instance {
+            let nodes = nodes.clone();
+            let anchor = self.anchor.clone();
+            owner.unmounted.borrow_mut().push(Box::new(move || {
+                remove_nodes(&nodes);
+                remove_nodes(std::slice::from_ref(&anchor));
+            }));
+        }
+
+        subscribe(disabled, move |disabled| {
+            if disabled {
+                insert_before(&nodes, &self.anchor);
+                return;
+            }
+
+            // Only looked up when needed, a disabled teleport may have no target
+            let Ok(Some(target)) = self.document.query_selector(&self.to) else {
+                let error = format!("Teleport target `{}` does not exist", self.to);
+                report_error(instance.clone(), &error.into());
+                insert_before(&nodes, &self.anchor);
+                return;
+            };
+            for node in &nodes {
+                target.append_child(node).unwrap();
+            }
+        });
+    }
+}
+
+/// A `<Suspense>`. Its content and fallback render into detached containers,
+/// and the fallback is shown until the async components in the content resolve.
+pub struct Suspense {
+    anchor: web_sys::Node,
+    content: web_sys::Element,
+    fallback: web_sys::Element,
+    /// The async components that have not resolved yet
+    pending: Cell<usize>,
+    mounted: Cell<bool>,
+    /// The nodes of the fallback while it is shown
+    shown_fallback: RefCell<Vec<web_sys::Node>>,
+    /// The instance of the content, mounted once it is shown
+    instance: RefCell<Weak<Instance>>,
+}
+
+impl Suspense {
+    pub fn new(document: &web_sys::Document, parent: &web_sys::Element) -> Rc<Self> {
+        let anchor: web_sys::Node = document.create_comment("suspense").into();
+        parent.append_child(&anchor).unwrap();
+
+        Rc::new(Self {
+            anchor,
+            content: document.create_element("div").unwrap(),
+            fallback: document.create_element("div").unwrap(),
+            pending: Cell::new(0),
+            mounted: Cell::new(false),
+            shown_fallback: RefCell::default(),
+            instance: RefCell::default(),
+        })
+    }
+
+    /// Makes this the boundary of the components rendered until the guard drops
+    pub fn enter(self: &Rc<Self>) -> InstanceGuard {
+        let instance = Instance::child_in(Instance::current().as_ref(), Some(self.clone()));
+        *self.instance.borrow_mut() = Rc::downgrade(&instance);
+        Instance::push(instance)
+    }
+
+    pub fn content(&self) -> web_sys::Element {
+        self.content.clone()
+    }
+
+    pub fn fallback(&self) -> web_sys::Element {
+        self.fallback.clone()
+    }
+
+    /// Shows the content if nothing is pending, and the fallback otherwise
+    pub fn mount(&self) {
+        self.mounted.set(true);
+        if self.pending.get() == 0 {
+            insert_before(&child_nodes(&self.content), &self.anchor);
+        } else {
+            let fallback = child_nodes(&self.fallback);
+            insert_before(&fallback, &self.anchor);
+            *self.shown_fallback.borrow_mut() = fallback;
+        }
+    }
+
+    fn suspend(&self) {
+        self.pending.set(self.pending.get() + 1);
+        if let Some(instance) = self.instance.borrow().upgrade() {
+            instance.pending.set(true);
+        }
+    }
+
+    /// Swaps the fallback for the content when the last async component resolved
+    fn resolve(&self) {
+        self.pending.set(self.pending.get() - 1);
+        if self.pending.get() > 0 || !self.mounted.get() {
+            return;
+        }
+
+        remove_nodes(&self.shown_fallback.take());
+        insert_before(&child_nodes(&self.content), &self.anchor);
+
+        if let Some(instance) = self.instance.borrow().upgrade() {
+            instance.pending.set(false);
+            if instance.parent_is_mounted() {
+                instance.mount();
+            }
+        }
+    }
+}
+
+/// Polls a future with `instance` as the current instance, so the script of an
+/// async component can register hooks after an `.await`
+struct WithInstance<F> {
+    instance: Option<Rc<Instance>>,
+    future: Pin<Box<F>>,
+}
+
+impl<F, T> Future for WithInstance<F>
+where
+    F: Future<Output = Result<T, Error>>,
+{
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        let _guard = self.instance.clone().map(Instance::push);
+        let future = self.future.as_mut();
+        // A panic ends the future like an error, where panics unwind
+        std::panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx)))
+            .unwrap_or_else(|panic| Poll::Ready(Err(panic_error(panic))))
+    }
+}
+
+/// Renders an async component: `setup` runs its script and resolves to the
+/// rendering of its template, which then renders in place. Until then the
+/// innermost `<Suspense>` shows its fallback.
+pub fn render_async<F, R>(document: &web_sys::Document, root: &web_sys::Element, setup: F)
+where
+    F: Future<Output = Result<R, Error>> + 'static,
+    R: FnOnce(&web_sys::Document, web_sys::Element) -> Result<(), Error> + 'static,
+{
+    let document = document.clone();
+    let anchor: web_sys::Node = document.create_comment("async").into();
+    root.append_child(&anchor).unwrap();
+
+    // The instance the render function of the component entered
+    let instance = Instance::current();
+    if let Some(instance) = &instance {
+        instance.pending.set(true);
+    }
+    let suspense = instance.as_ref().and_then(|instance| instance.suspense.clone());
+    if let Some(suspense) = &suspense {
+        suspense.suspend();
+    }
+
+    // Through `spawn`, so the component stops waiting once it is unmounted
+    spawn(async move {
+        let render = WithInstance {
+            instance: instance.clone(),
+            future: Box::pin(setup),
+        }
+        .await;
+        // The nodes around it were removed without unmounting the component
+        if anchor.parent_node().is_none() {
+            return;
+        }
+
+        let container = document.create_element("div").unwrap();
+        {
+            let _guard = instance.clone().map(Instance::push);
+            guarded(instance.as_ref(), || render?(&document, container.clone()));
+        }
+        insert_before(&child_nodes(&container), &anchor);
+
+        if let Some(instance) = &instance {
+            instance.pending.set(false);
+            // Inside a `<Suspense>` that still waits the content is mounted later
+            if instance.parent_is_mounted() {
+                instance.mount();
+            }
+        }
+        if let Some(suspense) = suspense {
+            suspense.resolve();
+        }
+    });
+}
+
+/// An `<ErrorBoundary>`. Its content and fallback render into detached
+/// containers, and the fallback replaces the content once an error of the
+/// content reaches the boundary.
+pub struct ErrorBoundary {
+    anchor: web_sys::Node,
+    content: web_sys::Element,
+    fallback: web_sys::Element,
+    failure: Rc<Failure>,
+    mounted: Cell<bool>,
+    /// The nodes of the content while it is shown
+    shown_content: RefCell<Vec<web_sys::Node>>,
+}
+
+impl ErrorBoundary {
+    pub fn new(document: &web_sys::Document, parent: &web_sys::Element) -> Rc<Self> {
+        let anchor: web_sys::Node = document.create_comment("error-boundary").into();
+        parent.append_child(&anchor).unwrap();
+
+        Rc::new(Self {
+            anchor,
+            content: document.create_element("div").unwrap(),
+            fallback: document.create_element("div").unwrap(),
+            failure: Rc::default(),
+            mounted: Cell::new(false),
+            shown_content: RefCell::default(),
+        })
+    }
+
+    /// Makes this the boundary of the components rendered until the guard
+    /// drops. Errors reaching it do not propagate further.
+    pub fn enter(self: &Rc<Self>) -> InstanceGuard {
+        let boundary = self.clone();
+        self.failure.enter(move || {
+            // Before it is mounted `mount` shows the fallback. Afterwards the
+            // content may still be rendering, e.g. an async component, so the
+            // swap waits until it has finished.
+            if boundary.mounted.get() {
+                let boundary = boundary.clone();
+                spawn_local(async move { boundary.show_fallback() });
+            }
+        })
+    }
+
+    pub fn content(&self) -> web_sys::Element {
+        self.content.clone()
+    }
+
+    pub fn fallback(&self) -> web_sys::Element {
+        self.fallback.clone()
+    }
+
+    /// The error message the `#fallback` template can bind to
+    pub fn error(&self) -> Mutable<String> {
+        self.failure.error.clone()
+    }
+
+    /// Shows the content, or the fallback if rendering the content failed
+    pub fn mount(&self) {
+        self.mounted.set(true);
+        if self.failure.failed.get() {
+            insert_before(&child_nodes(&self.fallback), &self.anchor);
+            self.failure.unmount();
+        } else {
+            let content = child_nodes(&self.content);
+            insert_before(&content, &self.anchor);
+            *self.shown_content.borrow_mut() = content;
+        }
+    }
+
+    /// Replaces the shown content with the fallback
+    fn show_fallback(&self) {
+        // The boundary was removed while the swap waited
+        if self.anchor.parent_node().is_none() {
+            return;
+        }
+        self.failure.before_unmount();
+        remove_nodes(&self.shown_content.take());
+        insert_before(&child_nodes(&self.fallback), &self.anchor);
+        self.failure.unmount();
+    }
+}
+
+/// The content instance of an `<ErrorBoundary>` and its first error, apart from
+/// the nodes the boundary shows
+#[derive(Default)]
+struct Failure {
+    /// The message of the first error, shown by the fallback
+    error: Mutable<String>,
+    failed: Cell<bool>,
+    /// The instance of the content, unmounted once it failed
+    instance: RefCell<Weak<Instance>>,
+}
+
+impl Failure {
+    /// Creates the instance of the content as a child of the current one. The
+    /// first error reaching it is recorded and `failed` is called. The instance
+    /// stays current until the guard drops, so the components rendered after the
+    /// failing one still register their bindings with it.
+    fn enter(self: &Rc<Self>, mut failed: impl FnMut() + 'static) -> InstanceGuard {
+        let instance = Instance::child_of(Instance::current().as_ref());
+        *self.instance.borrow_mut() = Rc::downgrade(&instance);
+
+        let failure = self.clone();
+        instance
+            .error_captured
+            .borrow_mut()
+            .push(Box::new(move |error| {
+                if !failure.failed.replace(true) {
+                    failure.error.set(error.to_string());
+                    failed();
+                }
+                false
+            }));
+
+        Instance::push(instance)
+    }
+
+    fn before_unmount(&self) {
+        if let Some(instance) = self.instance.borrow().upgrade() {
+            instance.before_unmount();
+        }
+    }
+
+    fn unmount(&self) {
+        if let Some(instance) = self.instance.borrow().upgrade() {
+            instance.unmount();
+        }
+    }
+}
+
+/// The handle of a `ref="name"` on an element, declared as `name` for the
+/// script. It holds the element once it is rendered.
+pub struct TemplateRef<T>(Rc<RefCell<Option<T>>>);
+
+impl<T> Default for TemplateRef<T> {
+    fn default() -> Self {
+        Self(Rc::default())
+    }
+}
+
+impl<T> Clone for TemplateRef<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: JsCast + Clone> TemplateRef<T> {
+    /// The element, `None` until it is rendered
+    pub fn get(&self) -> Option<T> {
+        self.0.borrow().clone()
+    }
+
+    pub fn set(&self, element: &web_sys::Element) {
+        *self.0.borrow_mut() = Some(element.clone().unchecked_into());
+    }
+}
+
+/// The handle of a `ref="name"` on a child component, which gives access to
+/// what the child passed to [`expose`]
+#[derive(Clone, Default)]
+pub struct ComponentRef(Rc<RefCell<Weak<Instance>>>);
+
+impl ComponentRef {
+    /// The API the child exposed, `None` until then or if it has another type
+    pub fn get<T: 'static>(&self) -> Option<Rc<T>> {
+        let instance = self.0.borrow().upgrade()?;
+        let exposed = instance.exposed.borrow().clone()?;
+        exposed.downcast::<T>().ok()
+    }
+
+    /// Points to the component the current one rendered last
+    pub fn set_last_child(&self) {
+        let child = Instance::current()
+            .and_then(|instance| instance.children.borrow().last().map(Rc::downgrade));
+        *self.0.borrow_mut() = child.unwrap_or_default();
+    }
+}
+
+/// Makes `api` available to the parent through a `ref` on the component
+pub fn expose<T: 'static>(api: T) {
+    *current_instance("expose").exposed.borrow_mut() = Some(Rc::new(api));
+}
+
+/// Identifies an [`InjectionKey`] by its name and the type of its value
+type InjectionId = (&'static str, TypeId);
+
+/// Names a value passed down the component tree with [`provide`] and [`inject`].
+/// Keys with the same name but different types don't collide.
+pub struct InjectionKey<T> {
+    name: &'static str,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static> InjectionKey<T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            value: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn id(&self) -> InjectionId {
+        (self.name, TypeId::of::<T>())
+    }
+}
+
+/// Makes `value` available to [`inject`] in the components the current one
+/// renders, and in the ones they render
+pub fn provide<T: 'static>(key: &InjectionKey<T>, value: T) {
+    current_instance("provide")
+        .provides
+        .borrow_mut()
+        .insert(key.id(), Rc::new(value));
+}
+
+/// Returns the value the closest ancestor provided for `key`. Panics, which
+/// reaches the error boundaries, if no ancestor provided one.
+pub fn inject<T: Clone + 'static>(key: &InjectionKey<T>) -> T {
+    try_inject(key).unwrap_or_else(|| {
+        panic!("No component above provides a value for the injection key `{}`", key.name)
+    })
+}
+
+/// Like [`inject`], but returns `default` if no ancestor provided a value
+pub fn inject_or<T: Clone + 'static>(key: &InjectionKey<T>, default: T) -> T {
+    try_inject(key).unwrap_or(default)
+}
+
+/// Like [`inject`], but returns `None` if no ancestor provided a value
+pub fn try_inject<T: Clone + 'static>(key: &InjectionKey<T>) -> Option<T> {
+    let instance = current_instance("inject");
+    let mut ancestor = instance.parent.as_ref().and_then(Weak::upgrade);
+    while let Some(instance) = ancestor {
+        if let Some(value) = instance.provides.borrow().get(&key.id()) {
+            return value.downcast_ref::<T>().cloned();
+        }
+        ancestor = instance.parent.as_ref().and_then(Weak::upgrade);
+    }
+
+    None
+}
+
+/// Runs the futures of the runtime where there is no browser event loop, such as
+/// in tests. They are queued until [`run_until_stalled`] polls them.
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::cell::RefCell;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Wake, Waker};
+
+    type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+    thread_local! {
+        /// The spawned futures by id, `None` once they have finished
+        static TASKS: RefCell<Vec<Option<Task>>> = const { RefCell::new(Vec::new()) };
+
+        /// The ids of the futures to poll next
+        static WOKEN: Arc<Mutex<Vec<usize>>> = Arc::default();
+    }
+
+    struct TaskWaker {
+        id: usize,
+        woken: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl Wake for TaskWaker {
+        fn wake(self: Arc<Self>) {
+            self.woken.lock().unwrap().push(self.id);
+        }
+    }
+
+    pub fn spawn_local(future: impl Future<Output = ()> + 'static) {
+        let id = TASKS.with(|tasks| {
+            let mut tasks = tasks.borrow_mut();
+            tasks.push(Some(Box::pin(future)));
+            tasks.len() - 1
+        });
+        WOKEN.with(|woken| woken.lock().unwrap().push(id));
+    }
+
+    /// Polls the spawned futures until all of them wait or have finished
+    pub fn run_until_stalled() {
+        let woken = WOKEN.with(Arc::clone);
+        loop {
+            let ids = std::mem::take(&mut *woken.lock().unwrap());
+            if ids.is_empty() {
+                return;
+            }
+
+            for id in ids {
+                // Taken out while polling, so the future may spawn others
+                let Some(mut task) = TASKS.with(|tasks| tasks.borrow_mut()[id].take()) else {
+                    continue;
+                };
+                let waker = Waker::from(Arc::new(TaskWaker {
+                    id,
+                    woken: woken.clone(),
+                }));
+                if task
+                    .as_mut()
+                    .poll(&mut Context::from_waker(&waker))
+                    .is_pending()
+                {
+                    TASKS.with(|tasks| tasks.borrow_mut()[id] = Some(task));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,441 @@
+/// Returns the id used to scope the styles of the component at `path`.
+/// The id is stable across builds and compiler versions.
+pub(crate) fn scope_id(path: &str) -> String {
+    // FNV-1a, since the output of `DefaultHasher` may change between Rust releases
+    let hash = path.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+
+    format!("{hash:08x}")
+}
+
+/// The attribute that marks the elements of a component with scoped styles
+pub(crate) fn scope_attribute(scope_id: &str) -> String {
+    format!("data-v-{scope_id}")
+}
+
+/// Rewrites every selector in `css` so it only matches elements carrying the
+/// scope attribute of `scope_id`. Supports Vue's `:deep()` and `:global()`
+/// pseudo classes, while `:slotted()` is rejected since components take no slot
+/// content yet.
+pub(crate) fn scope_css(css: &str, scope_id: &str) -> Result<String, String> {
+    let attribute = scope_attribute(scope_id);
+    let mut slotted = None;
+
+    let scoped = rewrite_selectors(css, &mut |selectors| {
+        if slotted.is_none() && selectors.contains(":slotted(") {
+            slotted = Some(selectors.to_string());
+        }
+        scope_selector_list(selectors, &attribute)
+    });
+
+    match slotted {
+        Some(selectors) => Err(format!(
+            "`:slotted()` in `{selectors}` can match nothing, since components do not take slot content yet"
+        )),
+        None => Ok(scoped),
+    }
+}
+
+/// Renames every class of a `<style module>` block to `{class}_{scope_id}` and
+/// returns the rewritten CSS together with the original class names, in the
+/// order they first appear. Classes inside `:global()` are kept as they are.
+pub(crate) fn module_css(css: &str, scope_id: &str) -> (String, Vec<String>) {
+    let mut classes = Vec::new();
+    let css = rewrite_selectors(css, &mut |selectors| {
+        rename_classes(selectors, scope_id, &mut classes)
+    });
+
+    (css, classes)
+}
+
+/// Calls `rewrite` with the selector list of every style rule in `css`
+fn rewrite_selectors(css: &str, rewrite: &mut dyn FnMut(&str) -> String) -> String {
+    let mut scoper = Scoper {
+        chars: css.chars().collect(),
+        position: 0,
+        rewrite,
+        scoped: String::with_capacity(css.len()),
+    };
+    scoper.rules();
+
+    scoper.scoped
+}
+
+/// At-rules whose blocks contain style rules that need to be scoped as well
+const NESTING_AT_RULES: [&str; 5] = ["media", "supports", "container", "layer", "document"];
+
+struct Scoper<'a> {
+    chars: Vec<char>,
+    position: usize,
+    rewrite: &'a mut dyn FnMut(&str) -> String,
+    scoped: String,
+}
+
+impl Scoper<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn starts_with(&self, pattern: &str) -> bool {
+        pattern
+            .chars()
+            .enumerate()
+            .all(|(offset, c)| self.chars.get(self.position + offset) == Some(&c))
+    }
+
+    /// Consumes a comment or string literal starting at the current position, if any
+    fn take_comment_or_string(&mut self) -> Option<String> {
+        let start = self.position;
+
+        if self.starts_with("/*") {
+            self.position += 2;
+            while self.peek().is_some() && !self.starts_with("*/") {
+                self.position += 1;
+            }
+            self.position = (self.position + 2).min(self.chars.len());
+        } else if let Some(quote @ ('"' | '\'')) = self.peek() {
+            self.position += 1;
+            while let Some(c) = self.peek() {
+                self.position += 1;
+                match c {
+                    '\\' => self.position += 1,
+                    _ if c == quote => break,
+                    _ => {}
+                }
+            }
+            self.position = self.position.min(self.chars.len());
+        } else {
+            return None;
+        }
+
+        Some(self.chars[start..self.position].iter().collect())
+    }
+
+    /// Scopes rules until the end of the input or the `}` closing the current block
+    fn rules(&mut self) {
+        loop {
+            let mut prelude = String::new();
+            while let Some(c) = self.peek() {
+                if matches!(c, '{' | '}' | ';') {
+                    break;
+                }
+
+                match self.take_comment_or_string() {
+                    // Comments in front of a rule are not part of its selector
+                    Some(comment) if comment.starts_with("/*") && prelude.trim().is_empty() => {
+                        self.scoped.push_str(&prelude);
+                        self.scoped.push_str(&comment);
+                        prelude.clear();
+                    }
+                    Some(skipped) => prelude.push_str(&skipped),
+                    None => {
+                        prelude.push(c);
+                        self.position += 1;
+                    }
+                }
+            }
+
+            match self.peek() {
+                Some(';') => {
+                    // A statement such as `@import "a.css";`
+                    self.scoped.push_str(&prelude);
+                    self.scoped.push(';');
+                    self.position += 1;
+                }
+                Some('{') => {
+                    let trimmed = prelude.trim();
+                    if let Some(at_rule) = trimmed.strip_prefix('@') {
+                        let name: String = at_rule
+                            .chars()
+                            .take_while(|c| c.is_alphanumeric() || *c == '-')
+                            .collect();
+
+                        self.scoped.push_str(&prelude);
+                        if NESTING_AT_RULES.contains(&name.as_str()) {
+                            self.scoped.push('{');
+                            self.position += 1;
+                            self.rules();
+                            self.scoped.push('}');
+                            self.position += 1;
+                        } else {
+                            // `@keyframes`, `@font-face` and the like select no elements
+                            self.copy_block();
+                        }
+                    } else {
+                        let leading = &prelude[..prelude.len() - prelude.trim_start().len()];
+                        self.scoped.push_str(leading);
+                        self.scoped.push_str(&(self.rewrite)(trimmed));
+                        self.scoped.push(' ');
+                        self.copy_block();
+                    }
+                }
+                // End of the enclosing block or of the input
+                _ => {
+                    self.scoped.push_str(&prelude);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Copies the block starting at the current `{` up to its matching `}`
+    fn copy_block(&mut self) {
+        let mut depth = 0usize;
+
+        while let Some(c) = self.peek() {
+            if let Some(skipped) = self.take_comment_or_string() {
+                self.scoped.push_str(&skipped);
+                continue;
+            }
+
+            self.scoped.push(c);
+            self.position += 1;
+
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Splits `selector` at every top level occurrence of `separator`, ignoring
+/// separators inside parentheses, brackets and strings
+fn split_top_level(selector: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+
+    for (index, c) in selector.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth = depth.saturating_sub(1),
+            (None, _) if c == separator && depth == 0 => {
+                parts.push(&selector[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&selector[start..]);
+
+    parts
+}
+
+fn scope_selector_list(selectors: &str, attribute: &str) -> String {
+    split_top_level(selectors, ',')
+        .into_iter()
+        .map(|selector| scope_selector(selector.trim(), attribute))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Finds `pseudo` such as `:deep(` and returns the text before it, its argument
+/// and the text after its closing parenthesis
+fn split_pseudo<'a>(selector: &'a str, pseudo: &str) -> Option<(&'a str, &'a str, &'a str)> {
+    let start = selector.find(pseudo)?;
+    let argument_start = start + pseudo.len();
+
+    let mut depth = 1usize;
+    for (offset, c) in selector[argument_start..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    let end = argument_start + offset;
+                    return Some((
+                        &selector[..start],
+                        &selector[argument_start..end],
+                        &selector[end + 1..],
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn scope_selector(selector: &str, attribute: &str) -> String {
+    if let Some((before, global, after)) = split_pseudo(selector, ":global(") {
+        return format!("{before}{global}{after}");
+    }
+
+    if let Some((before, deep, after)) = split_pseudo(selector, ":deep(") {
+        let before = before.trim_end();
+        let scoped = if before.is_empty() {
+            format!("[{attribute}]")
+        } else {
+            add_attribute(before, attribute)
+        };
+        return format!("{scoped} {}{after}", deep.trim());
+    }
+
+    add_attribute(selector, attribute)
+}
+
+/// Renames the classes in `selectors`, collecting their original names
+fn rename_classes(selectors: &str, scope_id: &str, classes: &mut Vec<String>) -> String {
+    if let Some((before, global, after)) = split_pseudo(selectors, ":global(") {
+        return format!(
+            "{}{global}{}",
+            rename_classes(before, scope_id, classes),
+            rename_classes(after, scope_id, classes)
+        );
+    }
+
+    let mut renamed = String::with_capacity(selectors.len());
+    let mut chars = selectors.chars().peekable();
+    let mut in_brackets = false;
+    let mut quote = None;
+
+    while let Some(c) = chars.next() {
+        renamed.push(c);
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[') => in_brackets = true,
+            (None, ']') => in_brackets = false,
+            (None, '.') if !in_brackets => {
+                let mut class = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '-' || c == '_') {
+                        break;
+                    }
+                    class.push(c);
+                    chars.next();
+                }
+
+                if !class.is_empty() {
+                    renamed.push_str(&format!("{class}_{scope_id}"));
+                    if !classes.contains(&class) {
+                        classes.push(class);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    renamed
+}
+
+/// Adds `[attribute]` to the last compound selector, in front of its pseudo
+/// classes and elements, e.g. `.a .b:hover` becomes `.a .b[attribute]:hover`
+fn add_attribute(selector: &str, attribute: &str) -> String {
+    let mut depth = 0usize;
+    let mut compound_start = 0;
+    let mut insert_at = None;
+
+    for (index, c) in selector.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            ' ' | '>' | '+' | '~' if depth == 0 => {
+                compound_start = index + 1;
+                insert_at = None;
+            }
+            ':' if depth == 0 && insert_at.is_none() && index >= compound_start => {
+                insert_at = Some(index);
+            }
+            _ => {}
+        }
+    }
+
+    let insert_at = insert_at.unwrap_or(selector.len());
+    format!(
+        "{}[{attribute}]{}",
+        &selector[..insert_at],
+        &selector[insert_at..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_id_is_stable() {
+        assert_eq!(scope_id("src/App.vue"), scope_id("src/App.vue"));
+        assert_ne!(scope_id("src/App.vue"), scope_id("src/Other.vue"));
+        assert_eq!(scope_id("src/App.vue").len(), 8);
+    }
+
+    #[test]
+    fn test_scopes_selectors() {
+        let cases = [
+            (".a", ".a[data-v-1]"),
+            (".a .b", ".a .b[data-v-1]"),
+            (".a > .b:hover", ".a > .b[data-v-1]:hover"),
+            ("p::before", "p[data-v-1]::before"),
+            ("a[href=\"x:y\"]", "a[href=\"x:y\"][data-v-1]"),
+            ("li:not(.a, .b)", "li[data-v-1]:not(.a, .b)"),
+            (".a :deep(.b)", ".a[data-v-1] .b"),
+            (":deep(.b)", "[data-v-1] .b"),
+            (":global(.b) .c", ".b .c"),
+        ];
+
+        for (selector, expected) in cases {
+            assert_eq!(scope_selector(selector, "data-v-1"), expected, "{selector}");
+        }
+    }
+
+    #[test]
+    fn test_renames_module_classes() {
+        let (css, classes) = module_css(
+            ".button, .button:hover .text-danger { width: 0.5em; }\n\
+             a[href=\".x\"] { } :global(.app) .card { }",
+            "1",
+        );
+
+        assert_eq!(
+            css,
+            ".button_1, .button_1:hover .text-danger_1 { width: 0.5em; }\n\
+             a[href=\".x\"] { } .app .card_1 { }"
+        );
+        assert_eq!(classes, vec!["button", "text-danger", "card"]);
+    }
+
+    #[test]
+    fn test_scopes_stylesheet() {
+        let css = r#"
+/* title { } */
+h1, .title:hover { color: red; content: "}"; }
+@import "other.css";
+@media (max-width: 600px) { .a { margin: 0; } }
+@keyframes spin { from { transform: rotate(0); } }
+"#;
+
+        assert_eq!(
+            scope_css(css, "1").unwrap(),
+            r#"
+/* title { } */
+h1[data-v-1], .title[data-v-1]:hover { color: red; content: "}"; }
+@import "other.css";
+@media (max-width: 600px) { .a[data-v-1] { margin: 0; } }
+@keyframes spin { from { transform: rotate(0); } }
+"#
+        );
+    }
+
+    #[test]
+    fn test_rejects_slotted() {
+        let error = scope_css("/* :slotted(.a) */ .b :slotted(.c) { }", "1").unwrap_err();
+
+        assert!(error.contains("`.b :slotted(.c)`"), "{error}");
+    }
+}
@@ -0,0 +1,64 @@
+//! Type-checks the runtime and the code generated for a component against
+//! `web_sys`, which the unit tests of the macro only see as text
+
+mod counter {
+    // `v-model` casts the event target like user code would
+    use wasm_bindgen::JsCast;
+
+    villain::create_component!("tests/fixtures/Counter.vue");
+
+    pub(crate) const THEME: villain_runtime::InjectionKey<&str> =
+        villain_runtime::InjectionKey::new("theme");
+
+    pub(crate) fn render(document: web_sys::Document, root: web_sys::Element) {
+        template(document, root);
+    }
+}
+
+/// Type-checks the code generated for a fixture of the expand tests
+macro_rules! fixture {
+    ($name:ident, $path:tt) => {
+        mod $name {
+            villain::create_component!($path);
+
+            pub(crate) fn render(document: web_sys::Document, root: web_sys::Element) {
+                template(document, root);
+            }
+        }
+    };
+}
+
+fixture!(bundle, "tests/fixtures/bundle/App.vue");
+fixture!(scoped_child, "tests/fixtures/scoped_child/App.vue");
+fixture!(dynamic, "tests/fixtures/dynamic/App.vue");
+fixture!(keep_alive, "tests/fixtures/keep_alive/App.vue");
+fixture!(suspense, "tests/fixtures/suspense/App.vue");
+fixture!(refs, "tests/fixtures/refs/App.vue");
+
+#[test]
+fn test_generated_code_compiles() {
+    let _: fn(web_sys::Document, web_sys::Element) = counter::render;
+    let _: fn(web_sys::Document, web_sys::Element) = bundle::render;
+    let _: fn(web_sys::Document, web_sys::Element) = scoped_child::render;
+    let _: fn(web_sys::Document, web_sys::Element) = dynamic::render;
+    let _: fn(web_sys::Document, web_sys::Element) = keep_alive::render;
+    let _: fn(web_sys::Document, web_sys::Element) = suspense::render;
+    let _: fn(web_sys::Document, web_sys::Element) = refs::render;
+}
+
+#[test]
+fn test_once_reads_current_value_without_subscribing() {
+    use counter::villain_runtime::{subscribe, Once};
+    use futures_signals::signal::Mutable;
+    use std::{cell::Cell, rc::Rc};
+
+    let count = Mutable::new(1);
+    let seen = Rc::new(Cell::new(0));
+    let binding = seen.clone();
+    // A subscription would only apply the value once its future is polled
+    subscribe(&Once(&count), move |count| binding.set(count));
+
+    assert_eq!(seen.get(), 1);
+    count.set(2);
+    assert_eq!(seen.get(), 1);
+}